@@ -0,0 +1,94 @@
+// Fragment stage for SamplingMode::Cubemap, which binds cube textures where custom.wgsl has 2d
+// ones. The vertex stage is the one of custom.wgsl.
+
+[[block]]
+struct View {
+    view_proj: mat4x4<f32>;
+    projection: mat4x4<f32>;
+    world_position: vec3<f32>;
+};
+[[group(0), binding(0)]]
+var<uniform> view: View;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] world_position: vec3<f32>;
+};
+
+[[group(1), binding(0)]]
+var texture: texture_cube<f32>;
+[[group(1), binding(1)]]
+var sampler: sampler;
+[[group(1), binding(2)]]
+var secondary_texture: texture_cube<f32>;
+
+[[block]]
+struct Display {
+    blend_factor: f32;
+    transition: u32;
+    placeholder: u32;
+    mirrored: u32;
+    placeholder_color: vec4<f32>;
+};
+[[group(1), binding(3)]]
+var<uniform> display: Display;
+
+
+[[block]]
+struct ViewSize {
+    size: vec2<f32>;
+    inverse_size: vec2<f32>;
+    viewport_offset: vec2<f32>;
+    aspect_ratio: f32;
+    time: f32;
+    frame: u32;
+};
+[[group(3), binding(0)]]
+var<uniform> view_size: ViewSize;
+
+fn random(seed: vec2<f32>) -> f32 {
+    return fract(sin(dot(seed, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// how much of the secondary texture is shown at this fragment
+fn transition_weight(uv: vec2<f32>, frag_coord: vec2<f32>) -> f32 {
+    let factor = display.blend_factor;
+    // wipe
+    if (display.transition == 1u) {
+        let edge = 0.05;
+        return 1.0 - smoothStep(factor * (1.0 + edge) - edge, factor * (1.0 + edge), uv.x);
+    }
+    // dissolve
+    if (display.transition == 2u) {
+        return step(random(floor(frag_coord)), factor);
+    }
+    // crossfade
+    return factor;
+}
+
+fn display_color(color: vec4<f32>, secondary_color: vec4<f32>, uv: vec2<f32>, frag_coord: vec2<f32>) -> vec4<f32> {
+    // no signal
+    if (display.placeholder == 1u) {
+        return display.placeholder_color;
+    }
+    if (display.placeholder == 2u) {
+        let frame = f32(view_size.frame % 256u);
+        let noise = random(floor(frag_coord * 0.5) + vec2<f32>(frame * 7.0, frame * 3.0));
+        return vec4<f32>(noise, noise, noise, 1.0);
+    }
+
+    return mix(color, secondary_color, transition_weight(uv, frag_coord));
+}
+
+[[stage(fragment)]]
+fn fragment_cubemap(out: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let direction = out.world_position - view.world_position;
+    // cubemaps are looked up in a left-handed space
+    let lookup = vec3<f32>(direction.x, direction.y, -direction.z);
+    let uv_view = out.clip_position.xy * view_size.inverse_size;
+
+    let color = textureSample(texture, sampler, lookup);
+    let secondary_color = textureSample(secondary_texture, sampler, lookup);
+    return display_color(color, secondary_color, uv_view, out.clip_position.xy);
+}
@@ -16,50 +16,145 @@ var<uniform> mesh: Mesh;
 
 struct Vertex {
     [[location(0)]] position: vec3<f32>;
-    [[location(1)]] normal: vec3<f32>;
-    [[location(2)]] uv: vec2<f32>;
+};
+
+struct UvVertex {
+    [[location(0)]] position: vec3<f32>;
+    [[location(1)]] uv: vec2<f32>;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
     [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] world_position: vec3<f32>;
 };
 
-[[stage(vertex)]]
-fn vertex(vertex: Vertex) -> VertexOutput {
-    let world_position = mesh.transform * vec4<f32>(vertex.position, 1.0);
+struct Instance {
+    [[location(2)]] transform_0: vec4<f32>;
+    [[location(3)]] transform_1: vec4<f32>;
+    [[location(4)]] transform_2: vec4<f32>;
+    [[location(5)]] transform_3: vec4<f32>;
+};
+
+fn instance_transform(instance: Instance) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.transform_0, instance.transform_1, instance.transform_2, instance.transform_3);
+}
+
+fn vertex_output(transform: mat4x4<f32>, position: vec3<f32>, uv: vec2<f32>) -> VertexOutput {
+    let world_position = transform * vec4<f32>(position, 1.0);
 
     var out: VertexOutput;
     out.clip_position = view.view_proj * world_position;
-    out.uv = vertex.uv;
-
+    out.uv = uv;
+    out.world_position = world_position.xyz;
     return out;
 }
 
-[[block]]
-struct CustomMaterial {
-    color: vec4<f32>;
-};
-[[group(1), binding(0)]]
-var<uniform> material: CustomMaterial;
+[[stage(vertex)]]
+fn vertex(vertex: Vertex) -> VertexOutput {
+    return vertex_output(mesh.transform, vertex.position, vec2<f32>(0.0, 0.0));
+}
+
+[[stage(vertex)]]
+fn vertex_uv(vertex: UvVertex) -> VertexOutput {
+    return vertex_output(mesh.transform, vertex.position, vertex.uv);
+}
+
+[[stage(vertex)]]
+fn vertex_instanced(vertex: Vertex, instance: Instance) -> VertexOutput {
+    return vertex_output(instance_transform(instance), vertex.position, vec2<f32>(0.0, 0.0));
+}
+
+[[stage(vertex)]]
+fn vertex_uv_instanced(vertex: UvVertex, instance: Instance) -> VertexOutput {
+    return vertex_output(instance_transform(instance), vertex.position, vertex.uv);
+}
 
 [[group(1), binding(0)]]
 var texture: texture_2d<f32>;
 [[group(1), binding(1)]]
 var sampler: sampler;
+[[group(1), binding(2)]]
+var secondary_texture: texture_2d<f32>;
+
+[[block]]
+struct Display {
+    blend_factor: f32;
+    transition: u32;
+    placeholder: u32;
+    mirrored: u32;
+    placeholder_color: vec4<f32>;
+};
+[[group(1), binding(3)]]
+var<uniform> display: Display;
 
 
 [[block]]
 struct ViewSize {
     size: vec2<f32>;
+    inverse_size: vec2<f32>;
+    viewport_offset: vec2<f32>;
+    aspect_ratio: f32;
+    time: f32;
+    frame: u32;
 };
 [[group(3), binding(0)]]
 var<uniform> view_size: ViewSize;
 
+fn random(seed: vec2<f32>) -> f32 {
+    return fract(sin(dot(seed, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+// how much of the secondary texture is shown at this fragment
+fn transition_weight(uv: vec2<f32>, frag_coord: vec2<f32>) -> f32 {
+    let factor = display.blend_factor;
+    // wipe
+    if (display.transition == 1u) {
+        let edge = 0.05;
+        return 1.0 - smoothStep(factor * (1.0 + edge) - edge, factor * (1.0 + edge), uv.x);
+    }
+    // dissolve
+    if (display.transition == 2u) {
+        return step(random(floor(frag_coord)), factor);
+    }
+    // crossfade
+    return factor;
+}
+
+fn display_color(color: vec4<f32>, secondary_color: vec4<f32>, uv: vec2<f32>, frag_coord: vec2<f32>) -> vec4<f32> {
+    // no signal
+    if (display.placeholder == 1u) {
+        return display.placeholder_color;
+    }
+    if (display.placeholder == 2u) {
+        let frame = f32(view_size.frame % 256u);
+        let noise = random(floor(frag_coord * 0.5) + vec2<f32>(frame * 7.0, frame * 3.0));
+        return vec4<f32>(noise, noise, noise, 1.0);
+    }
+
+    return mix(color, secondary_color, transition_weight(uv, frag_coord));
+}
+
+fn mirror_uv(uv: vec2<f32>) -> vec2<f32> {
+    if (display.mirrored == 1u) {
+        return vec2<f32>(1.0 - uv.x, uv.y);
+    }
+    return uv;
+}
+
 [[stage(fragment)]]
 fn fragment(out: VertexOutput) -> [[location(0)]] vec4<f32> {
-    let uv_view = vec2<f32>(out.clip_position.x / view_size.size.x, out.clip_position.y / view_size.size.y);
+    let uv_view = mirror_uv(out.clip_position.xy * view_size.inverse_size);
 
     let color = textureSample(texture, sampler, uv_view);
-    return color;
-}
\ No newline at end of file
+    let secondary_color = textureSample(secondary_texture, sampler, uv_view);
+    return display_color(color, secondary_color, uv_view, out.clip_position.xy);
+}
+
+[[stage(fragment)]]
+fn fragment_mesh_uv(out: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let uv = mirror_uv(out.uv);
+    let color = textureSample(texture, sampler, uv);
+    let secondary_color = textureSample(secondary_texture, sampler, uv);
+    return display_color(color, secondary_color, uv, out.clip_position.xy);
+}
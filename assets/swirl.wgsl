@@ -0,0 +1,46 @@
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[group(1), binding(0)]]
+var texture: texture_2d<f32>;
+[[group(1), binding(1)]]
+var sampler: sampler;
+
+[[block]]
+struct ViewSize {
+    size: vec2<f32>;
+    inverse_size: vec2<f32>;
+    viewport_offset: vec2<f32>;
+    aspect_ratio: f32;
+    time: f32;
+    frame: u32;
+};
+[[group(3), binding(0)]]
+var<uniform> view_size: ViewSize;
+
+fn swirl(uv: vec2<f32>) -> vec2<f32> {
+    let center = uv - vec2<f32>(0.5, 0.5);
+    let strength = 1.0 - smoothStep(0.0, 0.5, length(center));
+    let angle = strength * sin(view_size.time) * 2.0;
+
+    let s = sin(angle);
+    let c = cos(angle);
+    let rotated = vec2<f32>(c * center.x - s * center.y, s * center.x + c * center.y);
+    return rotated + vec2<f32>(0.5, 0.5);
+}
+
+[[stage(fragment)]]
+fn fragment(out: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let uv_view = out.clip_position.xy * view_size.inverse_size;
+
+    let color = textureSample(texture, sampler, swirl(uv_view));
+    return color;
+}
+
+[[stage(fragment)]]
+fn fragment_mesh_uv(out: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(texture, sampler, swirl(out.uv));
+    return color;
+}
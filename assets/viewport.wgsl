@@ -0,0 +1,35 @@
+[[group(0), binding(0)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(1)]]
+var sampler: sampler;
+
+[[block]]
+struct Viewport {
+    // top left corner and size, as fractions of the window size
+    position: vec2<f32>;
+    size: vec2<f32>;
+};
+[[group(0), binding(2)]]
+var<uniform> viewport: Viewport;
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+// a triangle strip of four vertices covering the viewport
+[[stage(vertex)]]
+fn vertex([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+    let window_uv = viewport.position + uv * viewport.size;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(window_uv.x * 2.0 - 1.0, 1.0 - window_uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fragment(out: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(texture, sampler, out.uv);
+}
@@ -0,0 +1,94 @@
+use bevy::ecs::prelude::*;
+use bevy::math::Vec3;
+use bevy::pbr2::{PbrBundle, PointLightBundle, StandardMaterial};
+use bevy::prelude::{App, Assets, GlobalTransform, Transform};
+use bevy::render2::camera::PerspectiveCameraBundle;
+use bevy::render2::color::Color;
+use bevy::render2::mesh::{shape, Mesh};
+use bevy::render2::texture::Image;
+use bevy::PipelinedDefaultPlugins;
+
+use bevy_portals::cam_display::{CamDisplay, CamDisplayPlugin};
+use bevy_portals::render_to_cubemap::{RenderToCubemap, RenderToCubemapPlugin};
+use bevy_portals::render_to_texture::RenderToTexturePlugin;
+use bevy_portals::screenspace_texture::{
+    SamplingMode, ScreenspaceTextureBundle, ScreenspaceTextureMaterial,
+};
+use bevy_portals::utils::Flycam;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(RenderToTexturePlugin)
+        .add_plugin(RenderToCubemapPlugin)
+        .add_plugin(CamDisplayPlugin)
+        .add_plugin(bevy_portals::utils::FlycamPlugin)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut sst_materials: ResMut<Assets<ScreenspaceTextureMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    // colored cubes around the probe, one on each side
+    let cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    let sides = [
+        (Vec3::X, Color::RED),
+        (-Vec3::X, Color::CYAN),
+        (Vec3::Y, Color::GREEN),
+        (-Vec3::Y, Color::PURPLE),
+        (Vec3::Z, Color::BLUE),
+        (-Vec3::Z, Color::YELLOW),
+    ];
+    let probe_position = Vec3::new(0.0, 2.0, -10.0);
+    for &(direction, color) in &sides {
+        commands.spawn_bundle(PbrBundle {
+            mesh: cube.clone(),
+            material: materials.add(color.into()),
+            transform: Transform::from_translation(probe_position + direction * 3.0),
+            ..Default::default()
+        });
+    }
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_translation(probe_position),
+        ..Default::default()
+    });
+
+    let probe = commands
+        .spawn()
+        .insert_bundle((
+            Transform::from_translation(probe_position),
+            GlobalTransform::default(),
+        ))
+        .insert(RenderToCubemap::new(&mut images, 512))
+        .id();
+
+    // a sphere looking into the surroundings of the probe
+    commands
+        .spawn_bundle(ScreenspaceTextureBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 1.0,
+                subdivisions: 4,
+            })),
+            material: sst_materials.add(ScreenspaceTextureMaterial {
+                sampling_mode: SamplingMode::Cubemap,
+                ..Default::default()
+            }),
+            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+            ..Default::default()
+        })
+        .insert(CamDisplay {
+            corresponding_camera: probe,
+        });
+
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        .insert(Flycam::default());
+}
@@ -0,0 +1,63 @@
+use bevy::ecs::prelude::*;
+use bevy::math::Vec3;
+use bevy::pbr2::{PbrBundle, PointLightBundle, StandardMaterial};
+use bevy::prelude::{App, Assets, Transform};
+use bevy::render2::camera::PerspectiveCameraBundle;
+use bevy::render2::color::Color;
+use bevy::render2::mesh::{shape, Mesh};
+use bevy::PipelinedDefaultPlugins;
+
+use bevy_portals::minimap::{Minimap, MinimapPlugin};
+use bevy_portals::render_to_texture::RenderToTexturePlugin;
+use bevy_portals::utils::Flycam;
+use bevy_portals::viewport::ViewportPlugin;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(RenderToTexturePlugin)
+        .add_plugin(ViewportPlugin)
+        .add_plugin(MinimapPlugin)
+        .add_plugin(bevy_portals::utils::FlycamPlugin)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 40.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    // a grid of pillars to find your way around
+    let pillar = meshes.add(Mesh::from(shape::Box::new(1.0, 3.0, 1.0)));
+    let pillar_material = materials.add(Color::rgb(0.8, 0.7, 0.6).into());
+    for x in -3..=3 {
+        for z in -3..=3 {
+            commands.spawn_bundle(PbrBundle {
+                mesh: pillar.clone(),
+                material: pillar_material.clone(),
+                transform: Transform::from_xyz(x as f32 * 5.0, 1.5, z as f32 * 5.0),
+                ..Default::default()
+            });
+        }
+    }
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(0.0, 20.0, 0.0),
+        ..Default::default()
+    });
+
+    let player = commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        .insert(Flycam::default())
+        .id();
+
+    commands.spawn().insert(Minimap::new(player));
+}
@@ -0,0 +1,78 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::ecs::prelude::*;
+use bevy::math::{Quat, Vec3};
+use bevy::pbr2::{PbrBundle, PointLightBundle, StandardMaterial};
+use bevy::prelude::{App, Assets, Transform};
+use bevy::render2::camera::PerspectiveCameraBundle;
+use bevy::render2::color::Color;
+use bevy::render2::mesh::{shape, Mesh};
+use bevy::PipelinedDefaultPlugins;
+
+use bevy_portals::cam_display::CamDisplayPlugin;
+use bevy_portals::mirror::{Mirror, MirrorPlugin};
+use bevy_portals::render_to_texture::RenderToTexturePlugin;
+use bevy_portals::screenspace_texture::{ScreenspaceTextureBundle, ScreenspaceTextureMaterial};
+use bevy_portals::utils::Flycam;
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(RenderToTexturePlugin)
+        .add_plugin(CamDisplayPlugin)
+        .add_plugin(MirrorPlugin)
+        .add_plugin(bevy_portals::utils::FlycamPlugin)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut sst_materials: ResMut<Assets<ScreenspaceTextureMaterial>>,
+) {
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 10.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    let cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    for (i, &color) in [Color::RED, Color::GREEN, Color::BLUE].iter().enumerate() {
+        commands.spawn_bundle(PbrBundle {
+            mesh: cube.clone(),
+            material: materials.add(color.into()),
+            transform: Transform::from_xyz(i as f32 * 2.0 - 2.0, 0.5, -1.0 - i as f32),
+            ..Default::default()
+        });
+    }
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(0.0, 6.0, 2.0),
+        ..Default::default()
+    });
+
+    let viewer = commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..Default::default()
+        })
+        .insert(Flycam::default())
+        .id();
+
+    // an upright mirror behind the cubes, facing them, which reflects from both sides
+    commands
+        .spawn_bundle(ScreenspaceTextureBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane { size: 4.0 })),
+            material: sst_materials.add(ScreenspaceTextureMaterial {
+                cull_mode: None,
+                ..Default::default()
+            }),
+            transform: Transform {
+                translation: Vec3::new(0.0, 2.0, -5.0),
+                rotation: Quat::from_rotation_x(FRAC_PI_2),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(Mirror::new(viewer));
+}
@@ -6,7 +6,7 @@ use bevy::render2::mesh::{shape, Mesh};
 use bevy::PipelinedDefaultPlugins;
 
 use bevy_portals::screenspace_texture::{
-    ScreenspaceTextureBundle, ScreenspaceTextureMaterial, ScreenspaceTexturePlugin,
+    SamplingMode, ScreenspaceTextureBundle, ScreenspaceTextureMaterial, ScreenspaceTexturePlugin,
 };
 use bevy_portals::utils::Flycam;
 
@@ -29,15 +29,30 @@ fn setup(
 
     commands.spawn().insert_bundle(ScreenspaceTextureBundle {
         mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
-        material: materials.add(ScreenspaceTextureMaterial { texture }),
+        material: materials.add(ScreenspaceTextureMaterial {
+            texture: texture.clone(),
+            ..Default::default()
+        }),
         transform: Transform::from_xyz(0.0, 0.5, 0.0),
         ..Default::default()
     });
 
+    commands.spawn().insert_bundle(ScreenspaceTextureBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(ScreenspaceTextureMaterial {
+            texture,
+            sampling_mode: SamplingMode::MeshUv,
+            custom_shader: Some(asset_server.load("swirl.wgsl")),
+            ..Default::default()
+        }),
+        transform: Transform::from_xyz(1.5, 0.5, 0.0),
+        ..Default::default()
+    });
+
     commands
         .spawn_bundle(PerspectiveCameraBundle {
             transform: Transform::from_xyz(-2.0, 2.5, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
             ..Default::default()
         })
-        .insert(Flycam);
+        .insert(Flycam::default());
 }
@@ -0,0 +1,75 @@
+use std::f32::consts::PI;
+
+use bevy::ecs::prelude::*;
+use bevy::math::{Quat, Vec2, Vec3};
+use bevy::pbr2::{PbrBundle, PointLightBundle, StandardMaterial};
+use bevy::prelude::{App, Assets, Transform};
+use bevy::render2::camera::PerspectiveCameraBundle;
+use bevy::render2::color::Color;
+use bevy::render2::mesh::{shape, Mesh};
+use bevy::render2::texture::Image;
+use bevy::PipelinedDefaultPlugins;
+
+use bevy_portals::render_to_texture::{RenderToTexture, RenderToTexturePlugin};
+use bevy_portals::viewport::{Viewport, ViewportPlugin};
+
+fn main() {
+    App::new()
+        .add_plugins(PipelinedDefaultPlugins)
+        .add_plugin(RenderToTexturePlugin)
+        .add_plugin(ViewportPlugin)
+        .add_startup_system(setup)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Plane { size: 10.0 })),
+        material: materials.add(Color::rgb(0.3, 0.5, 0.3).into()),
+        ..Default::default()
+    });
+    commands.spawn_bundle(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+        material: materials.add(Color::rgb(0.8, 0.7, 0.6).into()),
+        transform: Transform::from_xyz(0.0, 0.5, 0.0),
+        ..Default::default()
+    });
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..Default::default()
+    });
+
+    // two players side by side
+    let players = [
+        ("player 1", Vec3::new(-3.0, 2.5, 5.0)),
+        ("player 2", Vec3::new(3.0, 2.5, 5.0)),
+    ];
+    for (index, &(name, position)) in players.iter().enumerate() {
+        commands
+            .spawn_bundle(PerspectiveCameraBundle {
+                transform: Transform::from_translation(position).looking_at(Vec3::ZERO, Vec3::Y),
+                ..PerspectiveCameraBundle::with_name(name)
+            })
+            .insert(RenderToTexture::new(&mut images))
+            .insert(Viewport::grid(2, 1, index as u32));
+    }
+
+    // a rear view inset at the top of the first player's half
+    commands
+        .spawn_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_xyz(-3.0, 2.5, 5.0)
+                .looking_at(Vec3::ZERO, Vec3::Y)
+                .mul_transform(Transform::from_rotation(Quat::from_rotation_y(PI))),
+            ..PerspectiveCameraBundle::with_name("rear view")
+        })
+        .insert(RenderToTexture::new(&mut images))
+        .insert(Viewport {
+            order: 1,
+            ..Viewport::new(Vec2::new(0.15, 0.02), Vec2::new(0.2, 0.15))
+        });
+}
@@ -1,5 +1,8 @@
 use std::f32::consts::TAU;
+use std::io;
+use std::path::PathBuf;
 
+use bevy::asset::AssetServerSettings;
 use bevy::core::Name;
 use bevy::ecs::prelude::*;
 use bevy::math::prelude::*;
@@ -8,24 +11,67 @@ use bevy::prelude::{App, Assets, Transform};
 use bevy::render2::camera::{ActiveCameras, PerspectiveCameraBundle};
 use bevy::render2::color::Color;
 use bevy::render2::mesh::{shape, Mesh};
-use bevy::render2::texture::{BevyDefault, Image};
+use bevy::render2::texture::Image;
 use bevy::PipelinedDefaultPlugins;
 use bevy_inspector_egui::WorldInspectorPlugin;
 
-use bevy_portals::cam_display::{CamDisplay, CamDisplayPlugin};
+use bevy_portals::cam_display::{CamDisplay, CamDisplayPlugin, NoSignal};
+use bevy_portals::debug_dump;
+use bevy_portals::inspector::PortalInspectorPlugin;
 use bevy_portals::render_to_texture::{RenderToTexture, RenderToTexturePlugin};
-use bevy_portals::screenspace_texture::{ScreenspaceTextureBundle, ScreenspaceTextureMaterial};
+use bevy_portals::screenspace_texture::{
+    SSTShaderSettings, ScreenspaceTextureBundle, ScreenspaceTextureMaterial,
+};
 use bevy_portals::utils;
 
+/// Writes the render graph and schedules as DOT files into the given directory and exits,
+/// instead of running the app.
+const DUMP_GRAPHS_FLAG: &str = "--dump-graphs";
+
+/// Optional flycam settings, see [`utils::FlycamOptions::load`].
+const FLYCAM_CONFIG_PATH: &str = "flycam.ron";
+
 fn main() {
     let mut app = App::new();
+    // edits to the display shaders are picked up when built with the `hot_reload` feature
+    app.insert_resource(AssetServerSettings {
+        watch_for_changes: true,
+        ..Default::default()
+    })
+    .insert_resource(SSTShaderSettings { hot_reload: true });
+    match utils::FlycamOptions::load(FLYCAM_CONFIG_PATH) {
+        Ok(options) => {
+            app.insert_resource(options);
+        }
+        Err(utils::FlycamConfigError::Io(e)) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => eprintln!("ignoring {}: {}", FLYCAM_CONFIG_PATH, e),
+    }
     app.add_plugins(PipelinedDefaultPlugins)
         .add_plugin(RenderToTexturePlugin)
         .add_plugin(CamDisplayPlugin)
         .add_plugin(utils::FlycamPlugin)
         .add_plugin(WorldInspectorPlugin::new())
-        .add_startup_system(setup.system())
-        .run();
+        .add_plugin(PortalInspectorPlugin)
+        .add_startup_system(setup.system());
+
+    let mut args = std::env::args().skip(1);
+    if args.by_ref().any(|arg| arg == DUMP_GRAPHS_FLAG) {
+        let dir = match args.next() {
+            Some(arg) if arg.starts_with("--") => {
+                eprintln!("{} expects a directory, not {}", DUMP_GRAPHS_FLAG, arg);
+                std::process::exit(2);
+            }
+            Some(arg) => PathBuf::from(arg),
+            None => PathBuf::from("."),
+        };
+        if let Err(e) = debug_dump::write_dot_files(&mut app, &dir) {
+            eprintln!("failed to write graphs to {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    app.run();
 }
 
 fn setup(
@@ -36,12 +82,6 @@ fn setup(
     mut active_cameras: ResMut<ActiveCameras>,
     mut images: ResMut<Assets<Image>>,
 ) {
-    let cam_1_render_texture = images.add(dummy_image());
-    let cam_1_material_texture = images.add(dummy_image());
-
-    let cam_2_render_texture = images.add(dummy_image());
-    let cam_2_material_texture = images.add(dummy_image());
-
     let pos_portal_a = Vec3::new(-1.0, 1.0, -5.0 + 0.26);
     let pos_portal_b = Vec3::new(1.0, 2.0, -5.0 + 0.26);
 
@@ -54,7 +94,7 @@ fn setup(
             transform: Transform::from_xyz(-2.0, 2.5, 5.0), //.looking_at(Vec3::default(), Vec3::Y),
             ..Default::default()
         })
-        .insert(utils::Flycam)
+        .insert(utils::Flycam::default())
         .insert(Name::new("regular camera"));
 
     // Additional cameras
@@ -67,7 +107,9 @@ fn setup(
             },
             ..PerspectiveCameraBundle::with_name("additional camera 1")
         })
-        .insert(RenderToTexture(cam_1_render_texture))
+        .insert(RenderToTexture::new(&mut images))
+        // can be flown with the flycam's cycle key
+        .insert(utils::Flycam::default())
         .insert(Name::new("camera 1"))
         .id();
     active_cameras.add("additional camera 1");
@@ -81,7 +123,8 @@ fn setup(
             },
             ..PerspectiveCameraBundle::with_name("additional camera 2")
         })
-        .insert(RenderToTexture(cam_2_render_texture))
+        .insert(RenderToTexture::new(&mut images))
+        .insert(utils::Flycam::default())
         .insert(Name::new("camera 2"))
         .id();
     active_cameras.add("additional camera 2");
@@ -125,9 +168,7 @@ fn setup(
     commands
         .spawn_bundle(ScreenspaceTextureBundle {
             mesh: meshes.add(Mesh::from(shape::Plane { size: 1.0 })),
-            material: sst_materials.add(ScreenspaceTextureMaterial {
-                texture: cam_1_material_texture,
-            }),
+            material: sst_materials.add(ScreenspaceTextureMaterial::default()),
             transform: Transform {
                 translation: pos_portal_a,
                 rotation: rotation_display,
@@ -138,14 +179,13 @@ fn setup(
         .insert(Name::new("Plane 1"))
         .insert(CamDisplay {
             corresponding_camera: additional_cam_1,
-        });
+        })
+        .insert(NoSignal::Noise);
 
     commands
         .spawn_bundle(ScreenspaceTextureBundle {
             mesh: meshes.add(Mesh::from(shape::Plane { size: 1.0 })),
-            material: sst_materials.add(ScreenspaceTextureMaterial {
-                texture: cam_2_material_texture,
-            }),
+            material: sst_materials.add(ScreenspaceTextureMaterial::default()),
             transform: Transform {
                 translation: pos_portal_b,
                 rotation: rotation_display,
@@ -156,27 +196,6 @@ fn setup(
         .insert(Name::new("Plane 2"))
         .insert(CamDisplay {
             corresponding_camera: additional_cam_2,
-        });
-}
-
-fn dummy_image() -> Image {
-    use bevy::render2::render_resource::*;
-
-    Image {
-        data: vec![1; 4],
-        texture_descriptor: TextureDescriptor {
-            label: None,
-            size: Extent3d {
-                width: 1,
-                height: 1,
-                depth_or_array_layers: 1,
-            },
-            format: TextureFormat::bevy_default(),
-            dimension: TextureDimension::D2,
-            mip_level_count: 1,
-            sample_count: 1,
-            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_DST,
-        },
-        sampler_descriptor: SamplerDescriptor::default(),
-    }
+        })
+        .insert(NoSignal::Noise);
 }
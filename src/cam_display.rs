@@ -1,77 +1,394 @@
+use bevy::ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
 use bevy::ecs::prelude::*;
-use bevy::prelude::{App, Assets, Handle, Plugin};
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{
+    App, Assets, CoreStage, GlobalTransform, Handle, Plugin, Reflect, ReflectComponent, Time,
+};
 use bevy::render2::camera::Camera;
-use bevy::render2::render_resource::Extent3d;
+use bevy::render2::color::Color;
+use bevy::render2::mesh::{Mesh, VertexAttributeValues};
 use bevy::render2::texture::Image;
+use bevy::render2::RenderApp;
+use bevy::render2::RenderStage;
+use bevy::utils::HashMap;
 use bevy::window::Windows;
 
-use crate::render_to_texture::RenderToTexture;
-use crate::screenspace_texture::{ScreenspaceTextureMaterial, ScreenspaceTexturePlugin};
+use crate::render_to_cubemap::RenderToCubemap;
+use crate::render_to_texture::{
+    DisplayFootprint, RenderTargetSize, RenderToTexture, RenderToTextureSystem,
+};
+use crate::screenspace_texture::{
+    DisplayBlend, DisplayPlaceholder, DisplayTexture, SamplingMode, ScreenspaceTextureMaterial,
+    ScreenspaceTexturePlugin, TransitionKind,
+};
 
 pub struct CamDisplayPlugin;
 
 impl Plugin for CamDisplayPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(ScreenspaceTexturePlugin)
-            .add_system(swap_texture.label(CamDisplaySystem::SwapTextures))
+        app.register_type::<CamDisplay>()
+            .add_plugin(ScreenspaceTexturePlugin)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_cam_display_transitions.label(CamDisplaySystem::UpdateTransitions),
+            )
             .add_system(
-                resize_material_texture
-                    .label(CamDisplaySystem::ResizeMaterialTexture)
-                    .before(CamDisplaySystem::SwapTextures),
+                measure_display_footprints
+                    .label(CamDisplaySystem::MeasureFootprints)
+                    .before(RenderToTextureSystem::ResizeTexture),
             );
+
+        app.sub_app(RenderApp)
+            .add_system_to_stage(RenderStage::Extract, extract_cam_displays);
     }
 }
 
 #[derive(SystemLabel, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum CamDisplaySystem {
-    SwapTextures,
-    ResizeMaterialTexture,
+    /// Picks the cameras each display shows and advances their transitions, in
+    /// [`CoreStage::PostUpdate`]. The material's texture is no longer swapped, the images are
+    /// bound when the displays are extracted.
+    UpdateTransitions,
+    /// Measures the displays that size their camera's render target, before
+    /// [`RenderToTextureSystem::ResizeTexture`]. Display textures are no longer resized, they
+    /// are the camera's own images.
+    MeasureFootprints,
 }
 
+/// Shows the image of a [`RenderToTexture`] camera or a [`RenderToCubemap`] on a
+/// [`ScreenspaceTextureMaterial`], in place of the material's own texture.
+///
+/// `corresponding_camera` can be changed at any time. The display keeps showing the previous
+/// camera until the new one has rendered a frame and then animates the switch, see
+/// [`CamDisplayTransition`]. It shows its [`NoSignal`] placeholder (or the material's texture)
+/// while there is nothing to show.
+///
+/// In scenes, `corresponding_camera` is mapped to the spawned camera. A `CamDisplay` created by
+/// reflection before that points at `Entity::new(u32::MAX)`, a placeholder no camera is spawned
+/// as, so it shows its [`NoSignal`] placeholder until `corresponding_camera` is set.
+#[derive(Reflect)]
+#[reflect(Component, MapEntities)]
 pub struct CamDisplay {
     pub corresponding_camera: Entity,
 }
 
-fn swap_texture(
-    cam_displays: Query<(&CamDisplay, &Handle<ScreenspaceTextureMaterial>)>,
-    mut cameras: Query<&mut RenderToTexture, With<Camera>>,
-    mut standard_materials: ResMut<Assets<ScreenspaceTextureMaterial>>,
+impl MapEntities for CamDisplay {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.corresponding_camera = entity_map.get(self.corresponding_camera)?;
+        Ok(())
+    }
+}
+
+impl FromWorld for CamDisplay {
+    /// Points at the `Entity::new(u32::MAX)` placeholder, until `corresponding_camera` is set.
+    fn from_world(_: &mut World) -> Self {
+        CamDisplay {
+            corresponding_camera: Entity::new(u32::MAX),
+        }
+    }
+}
+
+/// Animates the switch to another camera when [`CamDisplay::corresponding_camera`] changes.
+/// Displays without it use the default half second crossfade, and a `duration` of 0 cuts to the
+/// new camera immediately.
+#[derive(Debug, Clone, Copy)]
+pub struct CamDisplayTransition {
+    pub kind: TransitionKind,
+    /// In seconds.
+    pub duration: f32,
+}
+
+impl Default for CamDisplayTransition {
+    fn default() -> Self {
+        CamDisplayTransition {
+            kind: TransitionKind::Crossfade,
+            duration: 0.5,
+        }
+    }
+}
+
+/// What a [`CamDisplay`] shows while its camera is missing, inactive or hasn't rendered a frame
+/// yet, see [`RenderToTexture::has_frame`]. Displays without it show their material's texture.
+#[derive(Debug, Clone)]
+pub enum NoSignal {
+    Color(Color),
+    /// A 2D image, which [`SamplingMode::Cubemap`] displays can't sample. They show black
+    /// instead.
+    Image(Handle<Image>),
+    /// Animated static.
+    Noise,
+}
+
+/// The camera a display is showing, and the one it is transitioning away from.
+struct CamDisplayState {
+    camera: Entity,
+    previous_camera: Option<Entity>,
+    progress: f32,
+}
+
+fn update_cam_display_transitions(
+    mut commands: Commands,
+    time: Res<Time>,
+    cameras: Query<(Option<&RenderToTexture>, Option<&RenderToCubemap>)>,
+    mut cam_displays: Query<(
+        Entity,
+        &CamDisplay,
+        Option<&CamDisplayTransition>,
+        Option<&mut CamDisplayState>,
+    )>,
 ) {
-    for (cam_display, display_material) in cam_displays.iter() {
-        let display_material = standard_materials.get_mut(display_material).unwrap();
-        let material_texture = &mut display_material.texture;
+    for (entity, cam_display, transition, state) in cam_displays.iter_mut() {
+        let transition = transition.copied().unwrap_or_default();
+        let mut state = match state {
+            Some(state) => state,
+            None => {
+                commands.entity(entity).insert(CamDisplayState {
+                    camera: cam_display.corresponding_camera,
+                    previous_camera: None,
+                    progress: 0.0,
+                });
+                continue;
+            }
+        };
+
+        let camera_is_ready = |camera| {
+            cameras
+                .get(camera)
+                .ok()
+                .and_then(|(render_to_texture, render_to_cubemap)| {
+                    camera_frame(render_to_texture, render_to_cubemap)
+                })
+                .is_some()
+        };
+        if state.camera != cam_display.corresponding_camera
+            && camera_is_ready(cam_display.corresponding_camera)
+        {
+            // switching again mid-transition continues from the camera that was switched to,
+            // and cuts if that one is gone
+            let previous_camera = Some(state.camera).filter(|&camera| camera_is_ready(camera));
+            state.previous_camera = previous_camera.filter(|_| transition.duration > 0.0);
+            state.camera = cam_display.corresponding_camera;
+            state.progress = 0.0;
+        }
 
-        let render_texture = &mut cameras.get_mut(cam_display.corresponding_camera).unwrap().0;
+        if state.previous_camera.is_some() {
+            if transition.duration > 0.0 {
+                state.progress += time.delta_seconds() / transition.duration;
+            } else {
+                state.progress = 1.0;
+            }
 
-        std::mem::swap(material_texture, render_texture);
+            if state.progress >= 1.0 {
+                state.previous_camera = None;
+            }
+        }
     }
 }
 
-fn resize_material_texture(
-    cam_displays: Query<(&CamDisplay, &Handle<ScreenspaceTextureMaterial>)>,
-    cameras: Query<&Camera, With<RenderToTexture>>,
-    standard_materials: Res<Assets<ScreenspaceTextureMaterial>>,
-    mut images: ResMut<Assets<Image>>,
+/// Measures how large the displays showing each [`RenderTargetSize::DisplayFootprint`] camera
+/// appear in the windows of the cameras which don't render to a texture.
+fn measure_display_footprints(
+    mut commands: Commands,
     windows: Res<Windows>,
+    meshes: Res<Assets<Mesh>>,
+    viewers: Query<(&Camera, &GlobalTransform), Without<RenderToTexture>>,
+    cam_displays: Query<(&CamDisplay, &Handle<Mesh>, &GlobalTransform)>,
+    mut cameras: Query<
+        (Entity, &RenderTargetSize, Option<&mut DisplayFootprint>),
+        With<RenderToTexture>,
+    >,
 ) {
-    for (cam_display, material) in cam_displays.iter() {
-        let material = standard_materials.get(material).unwrap();
+    let mut footprints: HashMap<Entity, Vec2> = HashMap::default();
+    for (cam_display, mesh_handle, display_transform) in cam_displays.iter() {
+        let (min, max) = match meshes.get(mesh_handle).and_then(mesh_bounds) {
+            Some(bounds) => bounds,
+            None => continue,
+        };
+        let corners = [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(max.x, max.y, max.z),
+        ];
 
-        let camera = cameras.get(cam_display.corresponding_camera).unwrap();
-        let window = windows.get(camera.window).unwrap();
+        for (viewer, viewer_transform) in viewers.iter() {
+            let window = match windows.get(viewer.window) {
+                Some(window) => window,
+                None => continue,
+            };
+            let window_size = Vec2::new(
+                window.physical_width() as f32,
+                window.physical_height() as f32,
+            );
+
+            let clip_from_local = viewer.projection_matrix
+                * viewer_transform.compute_matrix().inverse()
+                * display_transform.compute_matrix();
+            let mut ndc_min = Vec2::splat(f32::INFINITY);
+            let mut ndc_max = Vec2::splat(f32::NEG_INFINITY);
+            for &corner in &corners {
+                let clip = clip_from_local * corner.extend(1.0);
+                if clip.w <= 0.0 {
+                    // part of the display is behind the viewer, so it may cover the whole window
+                    ndc_min = Vec2::splat(-1.0);
+                    ndc_max = Vec2::splat(1.0);
+                    break;
+                }
+                let ndc = clip.truncate().truncate() / clip.w;
+                ndc_min = ndc_min.min(ndc);
+                ndc_max = ndc_max.max(ndc);
+            }
+
+            let on_screen = ndc_max.min(Vec2::ONE) - ndc_min.max(-Vec2::ONE);
+            if on_screen.x <= 0.0 || on_screen.y <= 0.0 {
+                continue;
+            }
+            let size = on_screen * 0.5 * window_size;
+
+            let footprint = footprints
+                .entry(cam_display.corresponding_camera)
+                .or_insert(Vec2::ZERO);
+            *footprint = footprint.max(size);
+        }
+    }
 
-        let new_size = Extent3d {
-            width: window.physical_width(),
-            height: window.physical_height(),
-            depth_or_array_layers: 1,
+    for (entity, target_size, display_footprint) in cameras.iter_mut() {
+        if !matches!(target_size, RenderTargetSize::DisplayFootprint { .. }) {
+            continue;
+        }
+        // cameras whose displays are all off screen keep their size
+        let footprint = match footprints.get(&entity) {
+            Some(footprint) => DisplayFootprint {
+                width: footprint.x.ceil() as u32,
+                height: footprint.y.ceil() as u32,
+            },
+            None => continue,
         };
+        // written in place so that the camera is resized this frame, only the first footprint
+        // has to wait for the command to be applied
+        match display_footprint {
+            Some(mut display_footprint) => {
+                if *display_footprint != footprint {
+                    *display_footprint = footprint;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(footprint);
+            }
+        }
+    }
+}
 
-        let texture = images.get(&material.texture).unwrap();
-        let texture_is_out_of_date = texture.texture_descriptor.size != new_size;
+fn mesh_bounds(mesh: &Mesh) -> Option<(Vec3, Vec3)> {
+    let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION)? {
+        VertexAttributeValues::Float32x3(positions) => positions,
+        _ => return None,
+    };
 
-        if texture_is_out_of_date {
-            let texture = images.get_mut(&material.texture).unwrap();
-            texture.resize(new_size);
+    let mut min = Vec3::splat(f32::INFINITY);
+    let mut max = Vec3::splat(f32::NEG_INFINITY);
+    for &position in positions {
+        min = min.min(Vec3::from(position));
+        max = max.max(Vec3::from(position));
+    }
+    (min.x <= max.x).then(|| (min, max))
+}
+
+#[allow(clippy::type_complexity)]
+fn extract_cam_displays(
+    mut commands: Commands,
+    cam_displays: Query<(
+        Entity,
+        &CamDisplay,
+        Option<&CamDisplayTransition>,
+        Option<&CamDisplayState>,
+        Option<&NoSignal>,
+        &Handle<ScreenspaceTextureMaterial>,
+    )>,
+    cameras: Query<(Option<&RenderToTexture>, Option<&RenderToCubemap>)>,
+    materials: Res<Assets<ScreenspaceTextureMaterial>>,
+) {
+    let frame = |camera| {
+        let (render_to_texture, render_to_cubemap) = cameras.get(camera).ok()?;
+        camera_frame(render_to_texture, render_to_cubemap)
+    };
+
+    for (entity, cam_display, transition, state, no_signal, material) in cam_displays.iter() {
+        let camera = state.map_or(cam_display.corresponding_camera, |state| state.camera);
+        let display_texture = match frame(camera) {
+            Some(display_texture) => display_texture,
+            None => {
+                match no_signal {
+                    Some(NoSignal::Color(color)) => {
+                        commands
+                            .get_or_spawn(entity)
+                            .insert(DisplayPlaceholder::Color(*color));
+                    }
+                    Some(NoSignal::Image(image)) => {
+                        let is_cubemap = materials.get(material).map_or(false, |material| {
+                            material.sampling_mode == SamplingMode::Cubemap
+                        });
+                        if is_cubemap {
+                            commands
+                                .get_or_spawn(entity)
+                                .insert(DisplayPlaceholder::Color(Color::BLACK));
+                        } else {
+                            commands
+                                .get_or_spawn(entity)
+                                .insert(DisplayTexture(image.clone_weak()));
+                        }
+                    }
+                    Some(NoSignal::Noise) => {
+                        commands
+                            .get_or_spawn(entity)
+                            .insert(DisplayPlaceholder::Noise);
+                    }
+                    None => {}
+                }
+                continue;
+            }
+        };
+
+        let previous_frame =
+            state.and_then(|state| Some((frame(state.previous_camera?)?, state.progress)));
+
+        // transitions show the previous camera, blended towards the current one
+        let mut entity = commands.get_or_spawn(entity);
+        match previous_frame {
+            Some((previous_display_texture, progress)) => {
+                entity.insert_bundle((
+                    DisplayTexture(previous_display_texture.clone_weak()),
+                    DisplayBlend {
+                        texture: display_texture.clone_weak(),
+                        factor: progress,
+                        transition: transition.copied().unwrap_or_default().kind,
+                    },
+                ));
+            }
+            None => {
+                entity.insert(DisplayTexture(display_texture.clone_weak()));
+            }
+        }
+    }
+}
+
+/// The image a display shows for a camera, once the camera has rendered a frame.
+fn camera_frame<'a>(
+    render_to_texture: Option<&'a RenderToTexture>,
+    render_to_cubemap: Option<&'a RenderToCubemap>,
+) -> Option<&'a Handle<Image>> {
+    match (render_to_texture, render_to_cubemap) {
+        (Some(render_to_texture), _) if render_to_texture.has_frame() => {
+            Some(&render_to_texture.display_texture)
+        }
+        (_, Some(render_to_cubemap)) if render_to_cubemap.has_frame() => {
+            Some(&render_to_cubemap.display_texture)
         }
+        _ => None,
     }
 }
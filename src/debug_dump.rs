@@ -0,0 +1,99 @@
+//! Graphviz DOT dumps of an app's render graph and schedules, e.g. to check where
+//! [`node::RENDER_TO_TEXTURE`](crate::render_to_texture::node::RENDER_TO_TEXTURE) runs relative to
+//! bevy's main pass. Render them with `dot -Tsvg render_graph.dot -o render_graph.svg`.
+
+use std::io;
+use std::path::Path;
+
+use bevy::prelude::App;
+use bevy::render2::render_graph::RenderGraph;
+use bevy::render2::RenderApp;
+use bevy_mod_debugdump::render_graph::render_graph_dot as render_graph_to_dot;
+use bevy_mod_debugdump::schedule_graph::schedule_graph_dot;
+
+/// The render graph of the app's render sub-app.
+pub fn render_graph_dot(app: &mut App) -> String {
+    let render_app = app.sub_app(RenderApp);
+    let render_graph = render_app.world.get_resource::<RenderGraph>().unwrap();
+    render_graph_to_dot(render_graph)
+}
+
+/// The stages and systems of the app's main schedule.
+pub fn schedule_dot(app: &App) -> String {
+    schedule_graph_dot(&app.schedule)
+}
+
+/// The stages and systems of the render sub-app's schedule.
+pub fn render_schedule_dot(app: &mut App) -> String {
+    schedule_graph_dot(&app.sub_app(RenderApp).schedule)
+}
+
+/// Writes `render_graph.dot`, `schedule.dot` and `render_schedule.dot` into `dir`, creating it
+/// if needed.
+pub fn write_dot_files(app: &mut App, dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("render_graph.dot"), render_graph_dot(app))?;
+    std::fs::write(dir.join("schedule.dot"), schedule_dot(app))?;
+    std::fs::write(dir.join("render_schedule.dot"), render_schedule_dot(app))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::core_pipeline::node::MAIN_PASS_DEPENDENCIES;
+    use bevy::ecs::schedule::SystemStage;
+    use bevy::render2::render_graph::EmptyNode;
+    use bevy::render2::RenderStage;
+
+    use super::*;
+    use crate::render_to_texture::{node, RenderToTexturePlugin};
+
+    /// An app with just enough of a render sub-app for plugins to add their systems and nodes,
+    /// without a GPU.
+    fn app_without_renderer() -> App {
+        let mut render_app = App::empty();
+        render_app
+            .add_stage(RenderStage::Extract, SystemStage::parallel())
+            .add_stage(RenderStage::Prepare, SystemStage::parallel())
+            .add_stage(RenderStage::Queue, SystemStage::parallel());
+        let mut render_graph = RenderGraph::default();
+        render_graph.add_node(MAIN_PASS_DEPENDENCIES, EmptyNode);
+        render_app.insert_resource(render_graph);
+
+        let mut app = App::new();
+        app.add_sub_app(RenderApp, render_app, |_, _| {});
+        app
+    }
+
+    /// Whether the DOT output has an edge between the nodes with the ids `from` and `to`.
+    fn has_edge(dot: &str, from: &str, to: &str) -> bool {
+        dot.lines().any(|line| match line.split_once("->") {
+            Some((tail, head)) => tail.contains(from) && head.contains(to),
+            None => false,
+        })
+    }
+
+    #[test]
+    fn render_to_texture_runs_before_main_pass() {
+        let mut app = app_without_renderer();
+        app.add_plugin(RenderToTexturePlugin);
+
+        let render_graph = app
+            .sub_app(RenderApp)
+            .world
+            .get_resource::<RenderGraph>()
+            .unwrap();
+        let id = |name| render_graph.get_node_id(name).unwrap().uuid().to_string();
+        let (render_to_texture, main_pass) =
+            (id(node::RENDER_TO_TEXTURE), id(MAIN_PASS_DEPENDENCIES));
+
+        let dot = render_graph_dot(&mut app);
+        assert!(
+            has_edge(&dot, &render_to_texture, &main_pass),
+            "no edge from {} to {} in\n{}",
+            node::RENDER_TO_TEXTURE,
+            MAIN_PASS_DEPENDENCIES,
+            dot
+        );
+    }
+}
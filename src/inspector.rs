@@ -0,0 +1,68 @@
+use bevy::core::Name;
+use bevy::ecs::prelude::*;
+use bevy::prelude::{App, Assets, Handle, Plugin};
+use bevy::render2::texture::Image;
+use bevy::utils::HashSet;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContext};
+use bevy_inspector_egui::{InspectorPlugin, RegisterInspectable};
+
+use crate::render_to_texture::RenderToTexture;
+use crate::screenspace_texture::ScreenspaceTextureMaterial;
+use crate::utils::FlycamOptions;
+
+/// Width of the previews in the render target window, in logical pixels.
+const PREVIEW_WIDTH: f32 = 160.0;
+
+/// Makes the display materials editable in the `WorldInspectorPlugin`, adds a window for the
+/// [`FlycamOptions`] and one previewing the image of every [`RenderToTexture`] camera.
+pub struct PortalInspectorPlugin;
+impl Plugin for PortalInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_inspectable::<Handle<ScreenspaceTextureMaterial>>()
+            .add_plugin(InspectorPlugin::<FlycamOptions>::new())
+            .add_system(render_target_previews);
+    }
+}
+
+/// Egui texture id of the preview of a camera, clear of the ids used by the inspector.
+fn preview_texture_id(camera: Entity) -> u64 {
+    (1 << 48) | camera.id() as u64
+}
+
+fn render_target_previews(
+    mut egui_context: ResMut<EguiContext>,
+    images: Res<Assets<Image>>,
+    cams: Query<(Entity, Option<&Name>, &RenderToTexture)>,
+    mut previewed: Local<HashSet<Entity>>,
+) {
+    let mut previews = Vec::new();
+    for (entity, name, render_to_texture) in cams.iter() {
+        // the display texture is the one that isn't being rendered to
+        let size = match images.get(&render_to_texture.display_texture) {
+            Some(image) => image.texture_descriptor.size,
+            None => continue,
+        };
+        let texture_id = preview_texture_id(entity);
+        egui_context.set_egui_texture(texture_id, render_to_texture.display_texture.clone());
+
+        let label = match name {
+            Some(name) => name.as_str().to_string(),
+            None => format!("{:?}", entity),
+        };
+        let height = PREVIEW_WIDTH * size.height as f32 / size.width.max(1) as f32;
+        previews.push((entity, label, texture_id, height));
+    }
+
+    let shown: HashSet<Entity> = previews.iter().map(|&(entity, ..)| entity).collect();
+    for &entity in previewed.difference(&shown) {
+        egui_context.remove_egui_texture(preview_texture_id(entity));
+    }
+    *previewed = shown;
+
+    egui::Window::new("Render targets").show(egui_context.ctx(), |ui| {
+        for (_, label, texture_id, height) in previews {
+            ui.label(label);
+            ui.image(egui::TextureId::User(texture_id), [PREVIEW_WIDTH, height]);
+        }
+    });
+}
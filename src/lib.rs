@@ -1,6 +1,13 @@
 pub mod utils;
 
 pub mod cam_display;
+pub mod debug_dump;
+pub mod inspector;
+pub mod minimap;
+pub mod mirror;
+pub mod render_to_cubemap;
 pub mod render_to_texture;
 
 pub mod screenspace_texture;
+pub mod view_size;
+pub mod viewport;
@@ -0,0 +1,144 @@
+use bevy::ecs::prelude::*;
+use bevy::math::{Vec2, Vec3};
+use bevy::prelude::{App, Assets, CoreStage, GlobalTransform, Plugin, Transform};
+use bevy::render2::camera::{Camera, OrthographicProjection, ScalingMode};
+use bevy::render2::texture::Image;
+use bevy::window::{WindowId, Windows};
+
+use crate::render_to_texture::RenderToTexture;
+use crate::viewport::Viewport;
+
+/// Turns the entity into a top-down orthographic [`RenderToTexture`] camera following `target`,
+/// shown as a square [`Viewport`] in a corner of `window`.
+///
+/// The map is oriented with -z up and drawn on top of other viewports.
+///
+/// Requires the [`RenderToTexturePlugin`](crate::render_to_texture::RenderToTexturePlugin) and
+/// the [`ViewportPlugin`](crate::viewport::ViewportPlugin).
+#[derive(Debug, Clone)]
+pub struct Minimap {
+    pub target: Entity,
+    pub window: WindowId,
+    pub corner: MinimapCorner,
+    /// Width and height of the map in physical pixels.
+    pub size: u32,
+    /// Distance to the window's edges in physical pixels.
+    pub margin: u32,
+    /// Distance from the target to the edges of the map, in world units.
+    pub radius: f32,
+    /// Height of the camera above the target. Everything above the camera is left out.
+    pub height: f32,
+}
+
+impl Minimap {
+    pub fn new(target: Entity) -> Self {
+        Minimap {
+            target,
+            window: WindowId::primary(),
+            corner: MinimapCorner::TopRight,
+            size: 256,
+            margin: 16,
+            radius: 20.0,
+            height: 50.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+pub struct MinimapPlugin;
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_minimap_cameras)
+            // after the transforms of the targets are propagated
+            .add_system_to_stage(CoreStage::Last, update_minimaps);
+    }
+}
+
+fn spawn_minimap_cameras(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    minimaps: Query<(Entity, &Minimap), Added<Minimap>>,
+) {
+    for (entity, minimap) in minimaps.iter() {
+        // the viewport is placed by `update_minimaps`
+        commands.entity(entity).insert_bundle((
+            Camera {
+                window: minimap.window,
+                ..Default::default()
+            },
+            OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical,
+                scale: minimap.radius,
+                far: minimap.height * 2.0,
+                ..Default::default()
+            },
+            Transform::default(),
+            GlobalTransform::default(),
+            RenderToTexture::new(&mut images),
+            Viewport {
+                order: 1,
+                ..Viewport::new(Vec2::ZERO, Vec2::ZERO)
+            },
+        ));
+    }
+}
+
+fn update_minimaps(
+    windows: Res<Windows>,
+    targets: Query<&GlobalTransform, Without<Minimap>>,
+    mut minimaps: Query<(
+        &Minimap,
+        &mut OrthographicProjection,
+        &mut Viewport,
+        &mut Transform,
+        &mut GlobalTransform,
+    )>,
+) {
+    for (minimap, mut projection, mut viewport, mut transform, mut global_transform) in
+        minimaps.iter_mut()
+    {
+        if let Ok(target_transform) = targets.get(minimap.target) {
+            let target = target_transform.translation;
+            let camera_transform = Transform::from_translation(target + Vec3::Y * minimap.height)
+                .looking_at(target, -Vec3::Z);
+            *transform = camera_transform;
+            *global_transform = GlobalTransform::from(camera_transform);
+        }
+
+        // the map extends `scale` units above and below its center
+        if projection.scale != minimap.radius || projection.far != minimap.height * 2.0 {
+            projection.scale = minimap.radius;
+            projection.far = minimap.height * 2.0;
+        }
+
+        let window = match windows.get(minimap.window) {
+            Some(window) => window,
+            None => continue,
+        };
+        let window_size = Vec2::new(
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        )
+        .max(Vec2::ONE);
+        let size = Vec2::splat(minimap.size as f32) / window_size;
+        let margin = Vec2::splat(minimap.margin as f32) / window_size;
+        let far_side = Vec2::ONE - margin - size;
+        let position = match minimap.corner {
+            MinimapCorner::TopLeft => margin,
+            MinimapCorner::TopRight => Vec2::new(far_side.x, margin.y),
+            MinimapCorner::BottomLeft => Vec2::new(margin.x, far_side.y),
+            MinimapCorner::BottomRight => far_side,
+        };
+        if viewport.position != position || viewport.size != size {
+            viewport.position = position;
+            viewport.size = size;
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use bevy::ecs::prelude::*;
+use bevy::math::{Mat4, Vec3, Vec4};
+use bevy::prelude::{App, Assets, CoreStage, GlobalTransform, Plugin, Transform};
+use bevy::render2::camera::Camera;
+use bevy::render2::texture::Image;
+use bevy::render2::{RenderApp, RenderStage};
+use bevy::window::{WindowId, Windows};
+
+use crate::cam_display::CamDisplay;
+use crate::render_to_texture::{RenderTargetSize, RenderToTexture};
+use crate::screenspace_texture::DisplayMirrored;
+
+/// Turns a [`ScreenspaceTextureMaterial`](crate::screenspace_texture::ScreenspaceTextureMaterial)
+/// display into a planar mirror for the `viewer` camera.
+///
+/// The mirror plane goes through the display's origin, along its local x and z axes like
+/// `shape::Plane`. A [`RenderToTexture`] camera reflected across it is spawned for every mirror
+/// and shown on it as a [`CamDisplay`], so the material should use
+/// [`SamplingMode::ScreenSpace`](crate::screenspace_texture::SamplingMode::ScreenSpace).
+/// The reflection works from both sides, but the material culls back faces by default, so a
+/// mirror is only seen from behind if its material's `cull_mode` is `None`.
+/// The viewer needs a perspective projection.
+///
+/// Requires the [`RenderToTexturePlugin`](crate::render_to_texture::RenderToTexturePlugin) and
+/// the [`CamDisplayPlugin`](crate::cam_display::CamDisplayPlugin).
+pub struct Mirror {
+    pub viewer: Entity,
+    /// How far in front of the surface the reflection is clipped, so that the mirror itself
+    /// doesn't show up in it.
+    pub clip_offset: f32,
+}
+
+impl Mirror {
+    pub fn new(viewer: Entity) -> Self {
+        Mirror {
+            viewer,
+            clip_offset: 0.01,
+        }
+    }
+}
+
+/// The camera rendering the reflection of a [`Mirror`].
+struct MirrorCamera {
+    mirror: Entity,
+}
+
+pub struct MirrorPlugin;
+impl Plugin for MirrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_mirror_cameras)
+            .add_system(despawn_mirror_cameras)
+            // after the transforms of the viewers are propagated, and after bevy's camera systems
+            .add_system_to_stage(CoreStage::Last, update_mirror_cameras);
+
+        app.sub_app(RenderApp)
+            .add_system_to_stage(RenderStage::Extract, extract_mirrors);
+    }
+}
+
+fn spawn_mirror_cameras(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mirrors: Query<Entity, Added<Mirror>>,
+) {
+    for mirror in mirrors.iter() {
+        // the camera has no window and no projection of its own, both are taken from the viewer
+        // by `update_mirror_cameras`
+        let camera = commands
+            .spawn_bundle((
+                Camera {
+                    window: WindowId::new(),
+                    ..Default::default()
+                },
+                Transform::default(),
+                GlobalTransform::default(),
+                RenderToTexture::new(&mut images),
+                RenderTargetSize::Fixed {
+                    width: 1,
+                    height: 1,
+                },
+                MirrorCamera { mirror },
+            ))
+            .id();
+
+        commands.entity(mirror).insert(CamDisplay {
+            corresponding_camera: camera,
+        });
+    }
+}
+
+fn despawn_mirror_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &MirrorCamera)>,
+    mirrors: Query<&CamDisplay, With<Mirror>>,
+) {
+    for (entity, camera) in cameras.iter() {
+        let is_shown = mirrors.get(camera.mirror).map_or(false, |cam_display| {
+            cam_display.corresponding_camera == entity
+        });
+        if !is_shown {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn update_mirror_cameras(
+    windows: Res<Windows>,
+    mirrors: Query<(&Mirror, &GlobalTransform), Without<MirrorCamera>>,
+    viewers: Query<(&Camera, &GlobalTransform, Option<&RenderToTexture>), Without<MirrorCamera>>,
+    mut cameras: Query<(
+        &MirrorCamera,
+        &mut Camera,
+        &mut Transform,
+        &mut GlobalTransform,
+        &mut RenderTargetSize,
+    )>,
+) {
+    for (mirror_camera, mut camera, mut transform, mut global_transform, mut target_size) in
+        cameras.iter_mut()
+    {
+        let (mirror, mirror_transform) = match mirrors.get(mirror_camera.mirror) {
+            Ok(mirror) => mirror,
+            Err(_) => continue,
+        };
+        let (viewer, viewer_transform, viewer_render_to_texture) = match viewers.get(mirror.viewer)
+        {
+            Ok(viewer) => viewer,
+            Err(_) => continue,
+        };
+
+        // the reflection is sampled in the viewer's screen space
+        let size = if viewer_render_to_texture.is_some() {
+            RenderTargetSize::MatchCamera(mirror.viewer)
+        } else {
+            match windows.get(viewer.window) {
+                Some(window) => RenderTargetSize::Fixed {
+                    width: window.physical_width(),
+                    height: window.physical_height(),
+                },
+                None => continue,
+            }
+        };
+        if *target_size != size {
+            *target_size = size;
+        }
+
+        let point = mirror_transform.translation;
+        let mut normal = mirror_transform.rotation * Vec3::Y;
+        if normal.dot(viewer_transform.translation - point) < 0.0 {
+            normal = -normal;
+        }
+        let reflect = |direction: Vec3| direction - 2.0 * normal.dot(direction) * normal;
+
+        // Reflecting the viewer's rotation would make it a reflection itself, which transforms
+        // can't hold and which reverses the winding of every triangle. Keeping the reflected
+        // forward and up directions gives a proper rotation instead, which only flips the
+        // image horizontally. The display flips it back, see `DisplayMirrored`.
+        let position = point + reflect(viewer_transform.translation - point);
+        let forward = reflect(viewer_transform.rotation * -Vec3::Z);
+        let up = reflect(viewer_transform.rotation * Vec3::Y);
+        let reflected = Transform::from_translation(position).looking_at(position + forward, up);
+        *transform = reflected;
+        *global_transform = GlobalTransform::from(reflected);
+
+        // everything between the camera and the mirror is behind the mirror from the viewer
+        let view = reflected.compute_matrix().inverse();
+        let view_normal = view.transform_vector3(normal);
+        let view_point = view.transform_point3(point + normal * mirror.clip_offset);
+        let clip_plane = view_normal.extend(-view_normal.dot(view_point));
+        let projection_matrix = oblique_projection(viewer.projection_matrix, clip_plane);
+        if camera.projection_matrix != projection_matrix {
+            camera.projection_matrix = projection_matrix;
+        }
+    }
+}
+
+/// Replaces the near plane of an infinite reverse-z perspective projection by `clip_plane`, given
+/// in view space with a unit normal facing the visible side.
+///
+/// The near plane keeps `z <= w`, so setting the z row to `w - k * clip_plane` keeps the points in
+/// front of `clip_plane`. Their depth falls from 1 at the plane towards
+/// `1 - k * distance / depth` at infinity, which `k` keeps above 0 inside the frustum.
+fn oblique_projection(projection: Mat4, clip_plane: Vec4) -> Mat4 {
+    // the largest distance from the camera per unit of view depth, along the frustum's edges
+    let max_distance_per_depth =
+        (1.0 + projection.x_axis.x.powi(-2) + projection.y_axis.y.powi(-2)).sqrt();
+    let k = 0.5 / max_distance_per_depth;
+
+    let w_row = projection.row(3);
+    Mat4::from_cols(
+        projection.row(0),
+        projection.row(1),
+        w_row - clip_plane * k,
+        w_row,
+    )
+    .transpose()
+}
+
+fn extract_mirrors(mut commands: Commands, mirrors: Query<Entity, With<Mirror>>) {
+    for entity in mirrors.iter() {
+        commands.get_or_spawn(entity).insert(DisplayMirrored);
+    }
+}
@@ -0,0 +1,247 @@
+use std::f32::consts::FRAC_PI_2;
+use std::num::NonZeroU32;
+
+use bevy::core_pipeline::Transparent3d;
+use bevy::ecs::prelude::*;
+use bevy::math::{Mat4, Vec3};
+use bevy::prelude::{App, Assets, GlobalTransform, Handle, Plugin, Transform};
+use bevy::render2::render_asset::RenderAssets;
+use bevy::render2::render_phase::RenderPhase;
+use bevy::render2::render_resource::{
+    Extent3d, SamplerDescriptor, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureId, TextureUsage, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+use bevy::render2::texture::{BevyDefault, GpuImage, Image};
+use bevy::render2::view::ExtractedView;
+use bevy::render2::{RenderApp, RenderStage};
+use bevy::utils::{HashMap, HashSet};
+
+use crate::render_to_texture::RenderToTextureSystem;
+
+/// Renders six 90° views from the entity's position into the layers of a cube texture,
+/// e.g. for reflection probes or 360° camera displays.
+///
+/// The faces are aligned to the world axes, regardless of the entity's rotation. Like
+/// [`RenderToTexture`](crate::render_to_texture::RenderToTexture), the views are rendered into
+/// `render_target` while `display_texture` holds the previous frame. Displays sample it with
+/// [`SamplingMode::Cubemap`](crate::screenspace_texture::SamplingMode::Cubemap).
+///
+/// Requires the [`RenderToTexturePlugin`](crate::render_to_texture::RenderToTexturePlugin),
+/// which drives the rendering of the faces.
+pub struct RenderToCubemap {
+    pub render_target: Handle<Image>,
+    pub display_texture: Handle<Image>,
+    /// Near plane of the face views.
+    pub near: f32,
+    /// Whether the faces rendering into `render_target` were extracted this frame.
+    render_target_rendered: bool,
+    /// Whether `display_texture` was rendered in the previous frame.
+    display_texture_rendered: bool,
+}
+
+impl RenderToCubemap {
+    /// Allocates both images with faces of `size` by `size` pixels.
+    pub fn new(images: &mut Assets<Image>, size: u32) -> Self {
+        RenderToCubemap {
+            render_target: images.add(cubemap_image(size)),
+            display_texture: images.add(cubemap_image(size)),
+            near: 0.1,
+            render_target_rendered: false,
+            display_texture_rendered: false,
+        }
+    }
+
+    /// Whether `display_texture` holds the frame rendered in the previous frame, like
+    /// [`RenderToTexture::has_frame`](crate::render_to_texture::RenderToTexture::has_frame).
+    pub fn has_frame(&self) -> bool {
+        self.display_texture_rendered
+    }
+
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.render_target, &mut self.display_texture);
+        self.display_texture_rendered = self.render_target_rendered;
+        self.render_target_rendered = false;
+    }
+}
+
+fn cubemap_image(size: u32) -> Image {
+    let size = size.max(1);
+    Image {
+        data: vec![0; size as usize * size as usize * 4 * 6],
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            format: TextureFormat::bevy_default(),
+            dimension: TextureDimension::D2,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+        },
+        sampler_descriptor: SamplerDescriptor::default(),
+    }
+}
+
+/// Forward and up direction of the view rendered into each layer.
+///
+/// Cubemaps are looked up in a left-handed space, so the display shader negates the z
+/// coordinate of its lookup direction, which swaps the z faces.
+fn cubemap_faces() -> [(Vec3, Vec3); 6] {
+    [
+        (Vec3::X, Vec3::Y),
+        (-Vec3::X, Vec3::Y),
+        (Vec3::Y, Vec3::Z),
+        (-Vec3::Y, -Vec3::Z),
+        (-Vec3::Z, Vec3::Y),
+        (Vec3::Z, Vec3::Y),
+    ]
+}
+
+pub struct RenderToCubemapPlugin;
+impl Plugin for RenderToCubemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(swap_cubemap_textures.label(RenderToTextureSystem::SwapTextures));
+
+        app.sub_app(RenderApp)
+            .init_resource::<CubemapViews>()
+            .add_system_to_stage(RenderStage::Extract, extract_cubemap_faces)
+            .add_system_to_stage(RenderStage::Prepare, prepare_cubemap_views);
+    }
+}
+
+fn swap_cubemap_textures(mut cubemaps: Query<&mut RenderToCubemap>) {
+    for mut cubemap in cubemaps.iter_mut() {
+        cubemap.swap();
+    }
+}
+
+/// The images of a [`RenderToCubemap`] in the render world.
+struct ExtractedCubemap {
+    render_target: Handle<Image>,
+    display_texture: Handle<Image>,
+}
+
+/// Render world view of one face of a [`RenderToCubemap`].
+pub struct CubemapFace {
+    pub render_target: Handle<Image>,
+    pub layer: u32,
+}
+
+/// Also records on the main world's [`RenderToCubemap`] that its `render_target` is rendered.
+fn extract_cubemap_faces(
+    mut commands: Commands,
+    mut cubemaps: Query<(Entity, &GlobalTransform, &mut RenderToCubemap)>,
+    images: Res<Assets<Image>>,
+) {
+    for (entity, transform, mut cubemap) in cubemaps.iter_mut() {
+        commands.get_or_spawn(entity).insert(ExtractedCubemap {
+            render_target: cubemap.render_target.clone_weak(),
+            display_texture: cubemap.display_texture.clone_weak(),
+        });
+
+        let size = match images.get(&cubemap.render_target) {
+            Some(image) => image.texture_descriptor.size.width,
+            None => continue,
+        };
+        cubemap.render_target_rendered = true;
+
+        let projection = Mat4::perspective_infinite_reverse_rh(FRAC_PI_2, 1.0, cubemap.near);
+        let position = transform.translation;
+
+        for (layer, &(forward, up)) in cubemap_faces().iter().enumerate() {
+            let transform =
+                Transform::from_translation(position).looking_at(position + forward, up);
+
+            commands.spawn_bundle((
+                ExtractedView {
+                    projection,
+                    transform: GlobalTransform::from(transform),
+                    width: size,
+                    height: size,
+                },
+                RenderPhase::<Transparent3d>::default(),
+                CubemapFace {
+                    render_target: cubemap.render_target.clone_weak(),
+                    layer: layer as u32,
+                },
+            ));
+        }
+    }
+}
+
+struct CubemapTextureViews {
+    cube: TextureView,
+    faces: Vec<TextureView>,
+}
+
+impl CubemapTextureViews {
+    fn new(texture: &Texture) -> Self {
+        let cube = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            array_layer_count: NonZeroU32::new(6),
+            ..Default::default()
+        });
+        let faces = (0..6)
+            .map(|layer| {
+                texture.create_view(&TextureViewDescriptor {
+                    dimension: Some(TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        CubemapTextureViews { cube, faces }
+    }
+}
+
+/// Cube and per-layer texture views of the images of every [`RenderToCubemap`], as the views
+/// created for images only cover them as a whole.
+#[derive(Default)]
+pub struct CubemapViews {
+    views: HashMap<TextureId, CubemapTextureViews>,
+}
+
+impl CubemapViews {
+    /// The view for sampling the image as a cubemap.
+    pub fn cube_view(&self, gpu_image: &GpuImage) -> Option<&TextureView> {
+        Some(&self.views.get(&gpu_image.texture.id())?.cube)
+    }
+
+    /// The view for rendering into one layer of the image.
+    pub fn face_view(&self, gpu_image: &GpuImage, layer: u32) -> Option<&TextureView> {
+        self.views
+            .get(&gpu_image.texture.id())?
+            .faces
+            .get(layer as usize)
+    }
+}
+
+fn prepare_cubemap_views(
+    cubemaps: Query<&ExtractedCubemap>,
+    gpu_images: Res<RenderAssets<Image>>,
+    mut cubemap_views: ResMut<CubemapViews>,
+) {
+    let mut used = HashSet::default();
+    for cubemap in cubemaps.iter() {
+        for handle in [&cubemap.render_target, &cubemap.display_texture] {
+            let gpu_image = match gpu_images.get(handle) {
+                Some(gpu_image) => gpu_image,
+                None => continue,
+            };
+
+            let id = gpu_image.texture.id();
+            used.insert(id);
+            cubemap_views
+                .views
+                .entry(id)
+                .or_insert_with(|| CubemapTextureViews::new(&gpu_image.texture));
+        }
+    }
+
+    cubemap_views.views.retain(|id, _| used.contains(id));
+}
@@ -1,30 +1,234 @@
 use bevy::core_pipeline::node::MAIN_PASS_DEPENDENCIES;
 use bevy::core_pipeline::{draw_3d_graph, Transparent3d, ViewDepthTexture};
+use bevy::ecs::entity::{EntityMap, MapEntities, MapEntitiesError};
 use bevy::ecs::prelude::*;
-use bevy::prelude::{App, Assets, Handle, Plugin};
-use bevy::render2::camera::Camera;
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::prelude::{
+    App, Assets, CoreStage, GlobalTransform, Handle, Plugin, Reflect, ReflectComponent,
+};
+use bevy::render2::camera::{
+    ActiveCameras, Camera, CameraProjection, OrthographicProjection, PerspectiveProjection,
+};
 use bevy::render2::render_asset::RenderAssets;
 use bevy::render2::render_graph::{self, RenderGraph, RenderGraphContext, SlotValue};
 use bevy::render2::render_phase::RenderPhase;
-use bevy::render2::render_resource::Extent3d;
+use bevy::render2::render_resource::{
+    Extent3d, SamplerDescriptor, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+};
 use bevy::render2::renderer::RenderContext;
-use bevy::render2::texture::Image;
+use bevy::render2::texture::{BevyDefault, Image};
+use bevy::render2::view::ExtractedView;
 use bevy::render2::{RenderApp, RenderStage};
+use bevy::utils::HashMap;
 use bevy::window::Windows;
 
+use serde::{Deserialize, Serialize};
+
+use crate::render_to_cubemap::{CubemapFace, CubemapViews};
+
 pub mod node {
     pub const RENDER_TO_TEXTURE: &str = "render_to_texture_node";
 }
 
-pub struct RenderToTexture(pub Handle<Image>);
+/// Renders the camera into an image instead of a window.
+///
+/// The camera doesn't need a window if its [`RenderTargetSize`] isn't
+/// [`RenderTargetSize::MatchWindow`], in which case its `window` can be any id without a window,
+/// e.g. `WindowId::new()`. Nor does it need to be one of bevy's [`ActiveCameras`].
+///
+/// The camera renders into `render_target` while `display_texture` holds the previous frame,
+/// and the two are swapped every frame. This way displays never sample the image that is being
+/// rendered to, even when the camera can see them.
+///
+/// The images only exist at runtime, so cameras in scene files should be given
+/// [`RenderTargetSettings`] instead, which allocate them when the scene is spawned.
+#[derive(Reflect)]
+#[reflect(Component)]
+pub struct RenderToTexture {
+    pub render_target: Handle<Image>,
+    pub display_texture: Handle<Image>,
+    /// Whether the view rendering into `render_target` was extracted this frame. Extracted images
+    /// are always prepared before the camera is rendered.
+    #[reflect(ignore)]
+    render_target_rendered: bool,
+    /// Whether `display_texture` was rendered in the previous frame.
+    #[reflect(ignore)]
+    display_texture_rendered: bool,
+}
+
+impl FromWorld for RenderToTexture {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.get_resource_mut::<Assets<Image>>().unwrap();
+        RenderToTexture::new(&mut images)
+    }
+}
+
+impl RenderToTexture {
+    /// Allocates both images. They are resized according to the camera's [`RenderTargetSize`]
+    /// before the first frame.
+    pub fn new(images: &mut Assets<Image>) -> Self {
+        RenderToTexture::with_format(images, TextureFormat::bevy_default())
+    }
+
+    /// Allocates both images with the given format, see [`RenderTargetFormat`].
+    pub fn with_format(images: &mut Assets<Image>, format: TextureFormat) -> Self {
+        RenderToTexture {
+            render_target: images.add(render_target_image(format)),
+            display_texture: images.add(render_target_image(format)),
+            render_target_rendered: false,
+            display_texture_rendered: false,
+        }
+    }
+
+    /// Whether `display_texture` holds the frame rendered in the previous frame at the current
+    /// size, rather than a blank, stretched or stale image.
+    ///
+    /// Cameras are rendered whether or not they are one of bevy's [`ActiveCameras`], so a camera
+    /// is inactive when it has no `Camera` component, e.g. after removing it to stop rendering,
+    /// or when its `render_target` image is missing. Inactive cameras have no frame once the
+    /// last frame they rendered has been displayed.
+    pub fn has_frame(&self) -> bool {
+        self.display_texture_rendered
+    }
+
+    fn swap(&mut self) {
+        std::mem::swap(&mut self.render_target, &mut self.display_texture);
+        self.display_texture_rendered = self.render_target_rendered;
+        self.render_target_rendered = false;
+    }
+}
+
+fn render_target_image(format: TextureFormat) -> Image {
+    Image {
+        data: vec![0; 4],
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            format,
+            dimension: TextureDimension::D2,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsage::RENDER_ATTACHMENT | TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+        },
+        sampler_descriptor: SamplerDescriptor::default(),
+    }
+}
+
+/// Describes the images of a [`RenderToTexture`] camera, which are allocated when this is added,
+/// e.g. when a scene is spawned, and again when the format changes. Also inserts the `size`
+/// as the camera's [`RenderTargetSize`].
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component, MapEntities)]
+pub struct RenderTargetSettings {
+    pub size: RenderTargetSize,
+    pub format: RenderTargetFormat,
+}
+
+impl MapEntities for RenderTargetSettings {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        if let RenderTargetSize::MatchCamera(camera) = &mut self.size {
+            *camera = entity_map.get(*camera)?;
+        }
+        Ok(())
+    }
+}
+
+/// The texture format of the images of a [`RenderToTexture`] camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum RenderTargetFormat {
+    /// [`TextureFormat::bevy_default`], the only format bevy's core pipeline renders into.
+    Default,
+    /// Half float HDR, for cameras rendered by custom pipelines. These cameras aren't extracted
+    /// or rendered by the [`node::RENDER_TO_TEXTURE`] node, as the core pipeline can't render into
+    /// them, so they never have a frame.
+    Rgba16Float,
+}
+impl Default for RenderTargetFormat {
+    fn default() -> Self {
+        RenderTargetFormat::Default
+    }
+}
+impl RenderTargetFormat {
+    pub fn texture_format(self) -> TextureFormat {
+        match self {
+            RenderTargetFormat::Default => TextureFormat::bevy_default(),
+            RenderTargetFormat::Rgba16Float => TextureFormat::Rgba16Float,
+        }
+    }
+}
+
+/// How the images of a [`RenderToTexture`] camera are sized. Cameras without it use
+/// [`RenderTargetSize::MatchWindow`].
+///
+/// Displays always sample the camera's images at whatever size they are, and the camera's
+/// [`PerspectiveProjection`] or [`OrthographicProjection`] is kept at their aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect_value(PartialEq, Serialize, Deserialize)]
+pub enum RenderTargetSize {
+    /// The physical size of the camera's window, which is what screen space displays
+    /// such as portals need. A portal camera should use the window of the camera looking
+    /// through the portal, which need not be the primary one.
+    MatchWindow,
+    /// A fixed size in pixels.
+    Fixed { width: u32, height: u32 },
+    /// The size of the images of another [`RenderToTexture`] camera. Portal cameras seen by
+    /// a camera rendering into a [`Viewport`](crate::viewport::Viewport) need its size rather
+    /// than the window's.
+    MatchCamera(Entity),
+    /// The largest size at which the displays showing the camera appear on screen, see
+    /// [`DisplayFootprint`]. The images are only reallocated once the footprint differs from
+    /// their size by more than the `hysteresis` fraction, e.g. `0.25`.
+    DisplayFootprint { hysteresis: f32 },
+}
+impl Default for RenderTargetSize {
+    fn default() -> Self {
+        RenderTargetSize::MatchWindow
+    }
+}
+
+/// The on-screen size in pixels of the displays showing a camera, measured by the
+/// [`CamDisplayPlugin`](crate::cam_display::CamDisplayPlugin) for cameras using
+/// [`RenderTargetSize::DisplayFootprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayFootprint {
+    pub width: u32,
+    pub height: u32,
+}
 
 pub struct RenderToTexturePlugin;
 impl Plugin for RenderToTexturePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(resize_rtt_texture.label(RenderToTextureSystem::ResizeTexture));
+        app.register_type::<RenderToTexture>()
+            .register_type::<RenderTargetSettings>()
+            .add_system(apply_render_target_settings.before(RenderToTextureSystem::SwapTextures))
+            .add_system(swap_rtt_textures.label(RenderToTextureSystem::SwapTextures))
+            .add_system(
+                resize_rtt_texture
+                    .label(RenderToTextureSystem::ResizeTexture)
+                    .after(RenderToTextureSystem::SwapTextures),
+            )
+            // after bevy's camera systems, which size every projection to its window
+            .add_system_to_stage(
+                CoreStage::Last,
+                update_rtt_projections::<PerspectiveProjection>,
+            )
+            .add_system_to_stage(
+                CoreStage::Last,
+                update_rtt_projections::<OrthographicProjection>,
+            );
 
         let render_app = app.sub_app(RenderApp);
-        render_app.add_system_to_stage(RenderStage::Extract, extract_rtt_render_phase);
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_rtt_render_phase)
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                apply_rtt_view_sizes.exclusive_system().at_start(),
+            );
 
         let mut render_graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
         render_graph.add_node(node::RENDER_TO_TEXTURE, SecondCamDriverNode::new());
@@ -36,54 +240,236 @@ impl Plugin for RenderToTexturePlugin {
 
 #[derive(SystemLabel, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum RenderToTextureSystem {
+    SwapTextures,
     ResizeTexture,
 }
 
+fn apply_render_target_settings(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    cams: Query<
+        (Entity, &RenderTargetSettings, Option<&RenderToTexture>),
+        Changed<RenderTargetSettings>,
+    >,
+) {
+    for (entity, settings, render_to_texture) in cams.iter() {
+        let mut entity = commands.entity(entity);
+        entity.insert(settings.size);
+
+        // a `RenderToTexture` loaded along with the settings refers to images that don't exist
+        let format = settings.format.texture_format();
+        let is_allocated = render_to_texture
+            .and_then(|render_to_texture| images.get(&render_to_texture.render_target))
+            .map_or(false, |image| image.texture_descriptor.format == format);
+        if !is_allocated {
+            entity.insert(RenderToTexture::with_format(&mut images, format));
+        }
+    }
+}
+
+/// Swaps the images of cameras without a `Camera` too, so that they stop having a frame.
+fn swap_rtt_textures(mut cams: Query<&mut RenderToTexture>) {
+    for mut render_to_texture in cams.iter_mut() {
+        render_to_texture.swap();
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn resize_rtt_texture(
-    cams: Query<(&RenderToTexture, &Camera)>,
+    mut cams: Query<(
+        Entity,
+        &mut RenderToTexture,
+        &Camera,
+        Option<&RenderTargetSize>,
+        Option<&DisplayFootprint>,
+    )>,
     mut images: ResMut<Assets<Image>>,
     windows: Res<Windows>,
 ) {
-    for (render_to_texture, camera) in cams.iter() {
-        let window = windows.get(camera.window).unwrap();
+    // sizes from before this frame's resizing, so that the order of the cameras doesn't matter
+    let camera_sizes: HashMap<Entity, Extent3d> = cams
+        .iter()
+        .filter_map(|(entity, render_to_texture, ..)| {
+            let image = images.get(&render_to_texture.render_target)?;
+            Some((entity, image.texture_descriptor.size))
+        })
+        .collect();
+
+    for (_, mut render_to_texture, camera, target_size, footprint) in cams.iter_mut() {
+        let (width, height) = match target_size.copied().unwrap_or_default() {
+            RenderTargetSize::MatchWindow => match windows.get(camera.window) {
+                Some(window) => (window.physical_width(), window.physical_height()),
+                None => continue,
+            },
+            RenderTargetSize::Fixed { width, height } => (width, height),
+            RenderTargetSize::MatchCamera(other) => match camera_sizes.get(&other) {
+                Some(size) => (size.width, size.height),
+                None => continue,
+            },
+            RenderTargetSize::DisplayFootprint { hysteresis } => {
+                let (footprint, current_size) =
+                    match (footprint, images.get(&render_to_texture.render_target)) {
+                        (Some(footprint), Some(image)) => {
+                            (footprint, image.texture_descriptor.size)
+                        }
+                        _ => continue,
+                    };
+
+                let exceeds = |current: u32, target: u32| {
+                    (target as f32 - current as f32).abs() > current as f32 * hysteresis
+                };
+                if !exceeds(current_size.width, footprint.width)
+                    && !exceeds(current_size.height, footprint.height)
+                {
+                    continue;
+                }
+                (footprint.width, footprint.height)
+            }
+        };
 
         let new_size = Extent3d {
-            width: window.physical_width(),
-            height: window.physical_height(),
+            width: width.max(1),
+            height: height.max(1),
             depth_or_array_layers: 1,
         };
 
-        let texture = images.get(&render_to_texture.0).unwrap();
-        let texture_is_out_of_date = texture.texture_descriptor.size != new_size;
+        let mut resized = false;
+        for handle in [
+            &render_to_texture.render_target,
+            &render_to_texture.display_texture,
+        ] {
+            let texture = match images.get(handle) {
+                Some(texture) => texture,
+                None => continue,
+            };
+            let texture_is_out_of_date = texture.texture_descriptor.size != new_size;
+
+            if texture_is_out_of_date {
+                let texture = images.get_mut(handle).unwrap();
+                texture.resize(new_size);
+                resized = true;
+            }
+        }
+
+        if resized {
+            render_to_texture.render_target_rendered = false;
+            render_to_texture.display_texture_rendered = false;
+        }
+    }
+}
+
+/// Keeps the aspect ratio of the projection at that of the images, which differs from
+/// the window's unless the camera uses [`RenderTargetSize::MatchWindow`]. Bevy doesn't update
+/// the projection of cameras without a window at all.
+fn update_rtt_projections<T: CameraProjection + Component + Clone>(
+    mut cams: Query<(&mut Camera, &mut T, &RenderToTexture)>,
+    images: Res<Assets<Image>>,
+) {
+    for (mut camera, mut projection, render_to_texture) in cams.iter_mut() {
+        let size = match images.get(&render_to_texture.render_target) {
+            Some(image) => image.texture_descriptor.size,
+            None => continue,
+        };
 
-        if texture_is_out_of_date {
-            let texture = images.get_mut(&render_to_texture.0).unwrap();
-            texture.resize(new_size);
+        // updated on a copy, so that unchanged projections aren't marked as changed
+        let mut updated = projection.clone();
+        updated.update(size.width as f32, size.height as f32);
+        let projection_matrix = updated.get_projection_matrix();
+        if projection.get_projection_matrix() != projection_matrix {
+            *projection = updated;
+        }
+        if camera.projection_matrix != projection_matrix {
+            camera.projection_matrix = projection_matrix;
         }
     }
 }
 
+/// The size of a camera's render target, which its [`ExtractedView`] is resized to.
+struct ExtractedTargetSize {
+    width: u32,
+    height: u32,
+}
+
+/// Whether the core pipeline can render into `image`, which it only does in the default format.
+pub(crate) fn is_renderable(image: &Image) -> bool {
+    image.texture_descriptor.format == TextureFormat::bevy_default()
+}
+
+/// Also records on the main world's [`RenderToTexture`] that its `render_target` is rendered.
 fn extract_rtt_render_phase(
     mut commands: Commands,
-    cams: Query<(Entity, &RenderToTexture), With<Camera>>,
+    mut cams: Query<(Entity, &Camera, &GlobalTransform, &mut RenderToTexture)>,
+    images: Res<Assets<Image>>,
+    windows: Res<Windows>,
+    active_cameras: Res<ActiveCameras>,
 ) {
-    for (entity, render_to_texture) in cams.iter() {
+    for (entity, camera, transform, mut render_to_texture) in cams.iter_mut() {
+        // bevy only extracts the views of active cameras with a window
+        let is_extracted = windows.get(camera.window).is_some()
+            && camera
+                .name
+                .as_ref()
+                .and_then(|name| active_cameras.get(name))
+                .map_or(false, |active_camera| active_camera.entity == Some(entity));
+
         let mut entity = commands.get_or_spawn(entity);
 
+        let image = images
+            .get(&render_to_texture.render_target)
+            .filter(|image| is_renderable(image));
+        if let Some(image) = image {
+            let size = image.texture_descriptor.size;
+            entity.insert(ExtractedTargetSize {
+                width: size.width,
+                height: size.height,
+            });
+
+            if !is_extracted {
+                entity.insert(ExtractedView {
+                    projection: camera.projection_matrix,
+                    transform: *transform,
+                    width: size.width,
+                    height: size.height,
+                });
+            }
+
+            render_to_texture.render_target_rendered = true;
+        }
+
         entity.insert(RenderPhase::<Transparent3d>::default());
-        entity.insert(RenderToTexture(render_to_texture.0.clone_weak()));
+        entity.insert(RenderToTexture {
+            render_target: render_to_texture.render_target.clone_weak(),
+            display_texture: render_to_texture.display_texture.clone_weak(),
+            render_target_rendered: render_to_texture.render_target_rendered,
+            display_texture_rendered: render_to_texture.display_texture_rendered,
+        });
+    }
+}
+
+/// Views of cameras with a window are extracted with the window's size. This runs before they are
+/// prepared, so that the depth texture and view uniforms match the render target instead.
+fn apply_rtt_view_sizes(mut views: Query<(&mut ExtractedView, &ExtractedTargetSize)>) {
+    for (mut view, target_size) in views.iter_mut() {
+        view.width = target_size.width;
+        view.height = target_size.height;
     }
 }
 
+/// Renders the views of every extracted [`RenderToTexture`] camera, and the faces of every
+/// [`RenderToCubemap`](crate::render_to_cubemap::RenderToCubemap).
 struct SecondCamDriverNode {
     query: Option<QueryState<Entity, With<RenderToTexture>>>,
+    cubemap_face_query: Option<QueryState<Entity, With<CubemapFace>>>,
     rtt_cameras: Vec<Entity>,
+    cubemap_faces: Vec<Entity>,
 }
 impl SecondCamDriverNode {
     fn new() -> SecondCamDriverNode {
         SecondCamDriverNode {
             query: None,
+            cubemap_face_query: None,
             rtt_cameras: Vec::new(),
+            cubemap_faces: Vec::new(),
         }
     }
 }
@@ -92,8 +478,13 @@ impl render_graph::Node for SecondCamDriverNode {
     fn update(&mut self, world: &mut World) {
         let query_state = self.query.get_or_insert_with(|| QueryState::new(world));
         query_state.update_archetypes(world);
-
         self.rtt_cameras = query_state.iter(world).collect();
+
+        let query_state = self
+            .cubemap_face_query
+            .get_or_insert_with(|| QueryState::new(world));
+        query_state.update_archetypes(world);
+        self.cubemap_faces = query_state.iter(world).collect();
     }
 
     fn run(
@@ -104,10 +495,18 @@ impl render_graph::Node for SecondCamDriverNode {
     ) -> Result<(), render_graph::NodeRunError> {
         for &camera_entity in &self.rtt_cameras {
             let render_to_texture = world.get::<RenderToTexture>(camera_entity).unwrap();
+            // only cameras with an image in the default format are extracted
+            if world.get::<ExtractedTargetSize>(camera_entity).is_none() {
+                continue;
+            }
             let depth_texture = world.get::<ViewDepthTexture>(camera_entity).unwrap();
 
             let image_render_assets = world.get_resource::<RenderAssets<Image>>().unwrap();
-            let gpu_image = &image_render_assets[&render_to_texture.0];
+            // skip the camera rather than panic if its image failed to prepare
+            let gpu_image = match image_render_assets.get(&render_to_texture.render_target) {
+                Some(gpu_image) => gpu_image,
+                None => continue,
+            };
 
             graph.run_sub_graph(
                 draw_3d_graph::NAME,
@@ -119,6 +518,146 @@ impl render_graph::Node for SecondCamDriverNode {
             )?;
         }
 
+        for &face_entity in &self.cubemap_faces {
+            let face = world.get::<CubemapFace>(face_entity).unwrap();
+            let depth_texture = world.get::<ViewDepthTexture>(face_entity).unwrap();
+
+            let image_render_assets = world.get_resource::<RenderAssets<Image>>().unwrap();
+            let cubemap_views = world.get_resource::<CubemapViews>().unwrap();
+            let face_view = match image_render_assets
+                .get(&face.render_target)
+                .and_then(|gpu_image| cubemap_views.face_view(gpu_image, face.layer))
+            {
+                Some(face_view) => face_view,
+                None => continue,
+            };
+
+            graph.run_sub_graph(
+                draw_3d_graph::NAME,
+                vec![
+                    SlotValue::Entity(face_entity),
+                    SlotValue::TextureView(face_view.clone()),
+                    SlotValue::TextureView(depth_texture.view.clone()),
+                ],
+            )?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::{AddAsset, AssetPlugin};
+    use bevy::core::CorePlugin;
+
+    /// Runs the systems tracking whether a [`RenderToTexture`] camera has a frame, and spawns a
+    /// camera with the given format. The returned handles keep its images alive, as the extracted
+    /// copy only holds weak handles.
+    fn app_with_camera(format: TextureFormat) -> (App, Entity, [Handle<Image>; 2]) {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<Image>()
+            .init_resource::<Windows>()
+            .init_resource::<ActiveCameras>()
+            .add_system(swap_rtt_textures)
+            // extracted into the main world itself, which is enough to see the flags
+            .add_system_to_stage(CoreStage::Last, extract_rtt_render_phase);
+
+        let render_to_texture = {
+            let mut images = app.world.get_resource_mut::<Assets<Image>>().unwrap();
+            RenderToTexture::with_format(&mut images, format)
+        };
+        let handles = [
+            render_to_texture.render_target.clone(),
+            render_to_texture.display_texture.clone(),
+        ];
+        let camera = app
+            .world
+            .spawn()
+            .insert_bundle((
+                Camera::default(),
+                GlobalTransform::default(),
+                render_to_texture,
+            ))
+            .id();
+        (app, camera, handles)
+    }
+
+    fn has_frame(app: &App, camera: Entity) -> bool {
+        app.world
+            .get::<RenderToTexture>(camera)
+            .unwrap()
+            .has_frame()
+    }
+
+    #[test]
+    fn default_format_cameras_have_a_frame() {
+        let (mut app, camera, _images) = app_with_camera(TextureFormat::bevy_default());
+        app.update();
+        assert!(!has_frame(&app, camera));
+        app.update();
+        assert!(has_frame(&app, camera));
+    }
+
+    #[test]
+    fn rgba16_float_cameras_never_have_a_frame() {
+        let (mut app, camera, _images) =
+            app_with_camera(RenderTargetFormat::Rgba16Float.texture_format());
+        for _ in 0..3 {
+            app.update();
+            assert!(!has_frame(&app, camera));
+        }
+    }
+
+    #[test]
+    fn cameras_lose_their_frame_without_camera() {
+        let (mut app, camera, _images) = app_with_camera(TextureFormat::bevy_default());
+        app.update();
+        app.update();
+        assert!(has_frame(&app, camera));
+
+        app.world.entity_mut(camera).remove::<Camera>();
+        // still shows the last rendered frame
+        app.update();
+        assert!(has_frame(&app, camera));
+        for _ in 0..2 {
+            app.update();
+            assert!(!has_frame(&app, camera));
+        }
+    }
+
+    #[test]
+    fn render_target_settings_map_match_camera() {
+        let mut entity_map = EntityMap::default();
+        entity_map.insert(Entity::new(1), Entity::new(7));
+
+        let mut settings = RenderTargetSettings {
+            size: RenderTargetSize::MatchCamera(Entity::new(1)),
+            format: RenderTargetFormat::Default,
+        };
+        settings.map_entities(&entity_map).unwrap();
+        assert_eq!(settings.size, RenderTargetSize::MatchCamera(Entity::new(7)));
+    }
+
+    #[test]
+    fn render_target_settings_without_camera_are_unmapped() {
+        let mut settings = RenderTargetSettings {
+            size: RenderTargetSize::Fixed {
+                width: 64,
+                height: 32,
+            },
+            format: RenderTargetFormat::Default,
+        };
+        settings.map_entities(&EntityMap::default()).unwrap();
+        assert_eq!(
+            settings.size,
+            RenderTargetSize::Fixed {
+                width: 64,
+                height: 32,
+            }
+        );
+    }
+}
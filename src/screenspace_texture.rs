@@ -1,12 +1,20 @@
+use std::num::{NonZeroU32, NonZeroU8};
+use std::ops::Range;
+
 use bevy::core_pipeline::Transparent3d;
 use bevy::ecs::prelude::*;
 use bevy::ecs::system::lifetimeless::*;
 use bevy::ecs::system::SystemParamItem;
-use bevy::math::Vec2;
+use bevy::log::{error, warn};
+use bevy::math::{Mat4, Vec4};
 use bevy::pbr2::{DrawMesh, MeshUniform, PbrShaders, SetMeshViewBindGroup, SetTransformBindGroup};
-use bevy::prelude::{AddAsset, App, GlobalTransform, Handle, Plugin, Transform};
+use bevy::prelude::{
+    AddAsset, App, AssetEvent, AssetServer, Assets, CoreStage, GlobalTransform, Handle, Plugin,
+    Transform,
+};
 use bevy::reflect::TypeUuid;
 
+use bevy::render2::color::Color;
 use bevy::render2::mesh::Mesh;
 use bevy::render2::render_asset::{
     PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets,
@@ -21,9 +29,14 @@ use bevy::render2::shader::Shader;
 use bevy::render2::texture::{BevyDefault, GpuImage, Image};
 use bevy::render2::view::ExtractedView;
 use bevy::render2::{RenderApp, RenderStage};
+use bevy::utils::{HashMap, HashSet};
 
+use bevy_inspector_egui::Inspectable;
 use crevice::std140::AsStd140;
 
+use crate::render_to_cubemap::CubemapViews;
+use crate::view_size::{SetViewSizeBindGroup, ViewSizeMeta, ViewSizePlugin};
+
 #[derive(Default, Bundle)]
 pub struct ScreenspaceTextureBundle {
     pub mesh: Handle<Mesh>,
@@ -32,99 +45,651 @@ pub struct ScreenspaceTextureBundle {
     pub transform: Transform,
 }
 
-#[derive(Debug, Clone, TypeUuid)]
+#[derive(Debug, Clone, TypeUuid, Inspectable)]
 #[uuid = "4ee9c363-1124-4113-890e-199d81b00281"]
 pub struct ScreenspaceTextureMaterial {
     // pub color: Color,
+    #[inspectable(ignore)]
     pub texture: Handle<Image>,
+    pub sampling_mode: SamplingMode,
+    /// Replaces the fragment stage of the display shader.
+    ///
+    /// The shader gets the same bindings as `custom.wgsl`: the texture, sampler, secondary texture
+    /// and display uniform in group 1, and the [`ViewSize`](crate::view_size::ViewSize) uniform in group 3.
+    /// Its entry point is `fragment` for [`SamplingMode::ScreenSpace`], `fragment_mesh_uv`
+    /// for [`SamplingMode::MeshUv`] and `fragment_cubemap` for [`SamplingMode::Cubemap`], taking
+    /// the `VertexOutput` of `custom.wgsl` as input. For [`SamplingMode::Cubemap`] the textures
+    /// are `texture_cube<f32>` instead, as in `cubemap.wgsl`.
+    #[inspectable(ignore)]
+    pub custom_shader: Option<Handle<Shader>>,
+    pub alpha_mode: AlphaMode,
+    /// Which faces to cull, `None` renders the mesh double-sided so that
+    /// e.g. a portal can be seen from behind.
+    #[inspectable(ignore)]
+    pub cull_mode: Option<Face>,
+    pub depth_write_enabled: bool,
+    #[inspectable(ignore)]
+    pub depth_compare: CompareFunction,
+    /// Overrides the sampler of the texture, which for render targets created at runtime
+    /// usually is whatever `SamplerDescriptor::default()` gives.
+    #[inspectable(ignore)]
+    pub sampler: Option<SamplerSettings>,
+    /// Shown on top of `texture` by `blend_factor`, sampled with the same sampler.
+    #[inspectable(ignore)]
+    pub secondary_texture: Option<Handle<Image>>,
+    /// How much of `secondary_texture` is shown, from 0 to 1.
+    #[inspectable(min = 0.0, max = 1.0)]
+    pub blend_factor: f32,
+    pub transition: TransitionKind,
+}
+impl Default for ScreenspaceTextureMaterial {
+    fn default() -> Self {
+        ScreenspaceTextureMaterial {
+            texture: Default::default(),
+            sampling_mode: Default::default(),
+            custom_shader: None,
+            alpha_mode: Default::default(),
+            cull_mode: Some(Face::Back),
+            depth_write_enabled: true,
+            depth_compare: CompareFunction::Greater,
+            sampler: None,
+            secondary_texture: None,
+            blend_factor: 0.0,
+            transition: Default::default(),
+        }
+    }
+}
+
+/// The subset of [`SamplerDescriptor`] that makes sense for a display texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerSettings {
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    /// Should be one of 1, 2, 4, 8 or 16, other values are rounded down to one of those. Only
+    /// used if all filters are [`FilterMode::Linear`].
+    pub anisotropy_clamp: Option<NonZeroU8>,
+}
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        SamplerSettings {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            anisotropy_clamp: None,
+        }
+    }
+}
+impl SamplerSettings {
+    /// Nearest neighbour filtering, for pixelated retro monitors.
+    pub fn nearest() -> Self {
+        SamplerSettings {
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    /// The `anisotropy_clamp` wgpu accepts for these settings, which panics on any other.
+    fn valid_anisotropy_clamp(&self) -> Option<NonZeroU8> {
+        let clamp = self.anisotropy_clamp?.get();
+        let linear = [self.mag_filter, self.min_filter, self.mipmap_filter]
+            .iter()
+            .all(|&filter| filter == FilterMode::Linear);
+        if !linear {
+            return None;
+        }
+        // the largest power of two up to 16 that isn't above the clamp
+        let power_of_two = 1 << (7 - clamp.min(16).leading_zeros());
+        NonZeroU8::new(power_of_two)
+    }
+
+    fn descriptor(&self) -> SamplerDescriptor<'static> {
+        let anisotropy_clamp = self.valid_anisotropy_clamp();
+        if anisotropy_clamp != self.anisotropy_clamp {
+            warn!(
+                "anisotropy_clamp {:?} isn't supported with {:?}, using {:?}",
+                self.anisotropy_clamp, self, anisotropy_clamp
+            );
+        }
+        SamplerDescriptor {
+            label: None,
+            address_mode_u: self.address_mode_u,
+            address_mode_v: self.address_mode_v,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            anisotropy_clamp,
+            ..Default::default()
+        }
+    }
+}
+
+/// Samplers created for [`SamplerSettings`], shared between all materials using the same settings.
+#[derive(Default)]
+pub struct SSTSamplers {
+    samplers: HashMap<SamplerSettings, Sampler>,
+}
+impl SSTSamplers {
+    pub fn get_or_create(
+        &mut self,
+        render_device: &RenderDevice,
+        settings: SamplerSettings,
+    ) -> &Sampler {
+        self.samplers
+            .entry(settings)
+            .or_insert_with(|| render_device.create_sampler(&settings.descriptor()))
+    }
+}
+
+/// Whether a display blends with what's behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Inspectable)]
+pub enum AlphaMode {
+    /// Ignore the alpha channel. Opaque displays are drawn before everything else in
+    /// the view, as the pipelined core pipeline only has a single `Transparent3d` phase.
+    Opaque,
+    /// Alpha blend the texture over the background, sorted back to front.
+    Blend,
+}
+impl Default for AlphaMode {
+    fn default() -> Self {
+        AlphaMode::Blend
+    }
+}
+
+/// How the secondary texture of a display replaces its texture as the blend factor goes from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Inspectable)]
+pub enum TransitionKind {
+    Crossfade = 0,
+    /// Sweeps from left to right, in the texture coordinates of the display.
+    Wipe = 1,
+    /// Replaces pixels in random order.
+    Dissolve = 2,
+}
+impl Default for TransitionKind {
+    fn default() -> Self {
+        TransitionKind::Crossfade
+    }
+}
+
+/// How the fragment shader maps the texture onto the mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Inspectable)]
+pub enum SamplingMode {
+    /// Sample at the fragment's position on screen. This is what portals want,
+    /// as the texture lines up with the view it was rendered from.
+    ScreenSpace,
+    /// Sample using the mesh's UV coordinates, so the texture sticks to the surface
+    /// like on a monitor.
+    MeshUv,
+    /// Sample a cube texture in the direction from the view to the fragment, for the images of a
+    /// [`RenderToCubemap`](crate::render_to_cubemap::RenderToCubemap).
+    Cubemap,
+}
+impl Default for SamplingMode {
+    fn default() -> Self {
+        SamplingMode::ScreenSpace
+    }
+}
+impl SamplingMode {
+    fn vertex_entry_point(self, instanced: bool) -> &'static str {
+        match (self, instanced) {
+            (SamplingMode::ScreenSpace | SamplingMode::Cubemap, false) => "vertex",
+            (SamplingMode::ScreenSpace | SamplingMode::Cubemap, true) => "vertex_instanced",
+            (SamplingMode::MeshUv, false) => "vertex_uv",
+            (SamplingMode::MeshUv, true) => "vertex_uv_instanced",
+        }
+    }
+
+    fn fragment_entry_point(self) -> &'static str {
+        match self {
+            SamplingMode::ScreenSpace => "fragment",
+            SamplingMode::MeshUv => "fragment_mesh_uv",
+            SamplingMode::Cubemap => "fragment_cubemap",
+        }
+    }
+}
+
+/// Where the attributes read by the display shader live in a mesh's interleaved vertex buffer.
+///
+/// [`Mesh`] sorts its attributes by name before interleaving them, so the offsets depend on
+/// which other attributes (normals, tangents, colors, ...) the mesh has.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SSTVertexLayout {
+    pub array_stride: u64,
+    pub position_offset: u64,
+    pub uv_offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SSTVertexLayoutError {
+    MissingAttribute(&'static str),
+    UnsupportedFormat(&'static str, VertexFormat),
+}
+impl std::fmt::Display for SSTVertexLayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SSTVertexLayoutError::MissingAttribute(name) => {
+                write!(f, "mesh is missing the `{}` attribute", name)
+            }
+            SSTVertexLayoutError::UnsupportedFormat(name, format) => {
+                write!(
+                    f,
+                    "mesh attribute `{}` has unsupported format {:?}",
+                    name, format
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for SSTVertexLayoutError {}
+
+impl SSTVertexLayout {
+    pub fn from_mesh(mesh: &Mesh) -> Result<Self, SSTVertexLayoutError> {
+        let mut array_stride = 0;
+        let mut position_offset = None;
+        let mut uv_offset = None;
+
+        for (name, values) in mesh.iter_attributes() {
+            let name: &str = name;
+            let format = VertexFormat::from(values);
+
+            if name == Mesh::ATTRIBUTE_POSITION {
+                if format != VertexFormat::Float32x3 {
+                    return Err(SSTVertexLayoutError::UnsupportedFormat(
+                        Mesh::ATTRIBUTE_POSITION,
+                        format,
+                    ));
+                }
+                position_offset = Some(array_stride);
+            } else if name == Mesh::ATTRIBUTE_UV_0 && format == VertexFormat::Float32x2 {
+                uv_offset = Some(array_stride);
+            }
+
+            array_stride += format.size();
+        }
+
+        let position_offset = position_offset.ok_or(SSTVertexLayoutError::MissingAttribute(
+            Mesh::ATTRIBUTE_POSITION,
+        ))?;
+
+        Ok(SSTVertexLayout {
+            array_stride,
+            position_offset,
+            uv_offset,
+        })
+    }
+}
+
+/// Vertex layouts of the meshes used with a [`ScreenspaceTextureMaterial`], kept up to date in
+/// the main world and extracted onto every entity with one.
+#[derive(Default)]
+struct SSTVertexLayouts {
+    layouts: HashMap<Handle<Mesh>, Result<SSTVertexLayout, SSTVertexLayoutError>>,
+}
+
+fn update_sst_vertex_layouts(
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    displays: Query<&Handle<Mesh>, With<Handle<ScreenspaceTextureMaterial>>>,
+    mut vertex_layouts: ResMut<SSTVertexLayouts>,
+) {
+    for event in mesh_events.iter() {
+        match event {
+            AssetEvent::Created { handle }
+            | AssetEvent::Modified { handle }
+            | AssetEvent::Removed { handle } => {
+                vertex_layouts.layouts.remove(handle);
+            }
+        }
+    }
+
+    // only meshes that are actually displayed have to fit the display shader
+    for handle in displays.iter() {
+        if vertex_layouts.layouts.contains_key(handle) {
+            continue;
+        }
+        let mesh = match meshes.get(handle) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+        let layout = SSTVertexLayout::from_mesh(mesh);
+        if let Err(e) = &layout {
+            warn!(
+                "mesh {:?} can't be displayed with a ScreenspaceTextureMaterial: {}",
+                handle.id, e
+            );
+        }
+        vertex_layouts.layouts.insert(handle.clone_weak(), layout);
+    }
+}
+
+fn extract_sst_vertex_layouts(
+    mut commands: Commands,
+    vertex_layouts: Res<SSTVertexLayouts>,
+    query: Query<(Entity, &Handle<Mesh>), With<Handle<ScreenspaceTextureMaterial>>>,
+) {
+    for (entity, mesh) in query.iter() {
+        if let Some(Ok(layout)) = vertex_layouts.layouts.get(mesh) {
+            commands.get_or_spawn(entity).insert(layout.clone());
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct GpuScreenspaceTextureMaterial {
-    // _buffer: Buffer,
-    bind_group: BindGroup,
+    texture: Handle<Image>,
+    sampler: Option<SamplerSettings>,
+    sampling_mode: SamplingMode,
+    custom_shader: Option<Handle<Shader>>,
+    alpha_mode: AlphaMode,
+    cull_mode: Option<Face>,
+    depth_write_enabled: bool,
+    depth_compare: CompareFunction,
+    secondary_texture: Option<Handle<Image>>,
+    blend_factor: f32,
+    transition: TransitionKind,
 }
 
 impl RenderAsset for ScreenspaceTextureMaterial {
     type ExtractedAsset = ScreenspaceTextureMaterial;
     type PreparedAsset = GpuScreenspaceTextureMaterial;
-    type Param = (
-        SRes<RenderDevice>,
-        SRes<SSTShaders>,
-        SRes<RenderAssets<Image>>,
-    );
+    type Param = ();
 
     fn extract_asset(&self) -> Self::ExtractedAsset {
         self.clone()
     }
 
+    /// The bind group isn't created here but in `queue_sst`, since the texture may be
+    /// overridden per entity by a [`DisplayTexture`].
     fn prepare_asset(
         extracted_asset: Self::ExtractedAsset,
-        (render_device, custom_pipeline, gpu_images): &mut SystemParamItem<Self::Param>,
+        _: &mut SystemParamItem<Self::Param>,
     ) -> Result<Self::PreparedAsset, PrepareAssetError<Self::ExtractedAsset>> {
-        // color
-        /* let color: Vec4 = extracted_asset.color.as_rgba_linear().into();
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            contents: color.as_std140().as_bytes(),
-            label: None,
-            usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
-        }); */
+        Ok(GpuScreenspaceTextureMaterial {
+            texture: extracted_asset.texture.clone_weak(),
+            sampler: extracted_asset.sampler,
+            sampling_mode: extracted_asset.sampling_mode,
+            custom_shader: extracted_asset
+                .custom_shader
+                .map(|shader| shader.clone_weak()),
+            alpha_mode: extracted_asset.alpha_mode,
+            cull_mode: extracted_asset.cull_mode,
+            depth_write_enabled: extracted_asset.depth_write_enabled,
+            depth_compare: extracted_asset.depth_compare,
+            secondary_texture: extracted_asset
+                .secondary_texture
+                .map(|texture| texture.clone_weak()),
+            blend_factor: extracted_asset.blend_factor,
+            transition: extracted_asset.transition,
+        })
+    }
+}
 
-        // texture
-        let gpu_image: &GpuImage = match gpu_images.get(&extracted_asset.texture) {
-            Some(gpu_image) => gpu_image,
-            None => return Err(PrepareAssetError::RetryNextUpdate(extracted_asset)),
-        };
+/// Render world component which makes an entity sample this image instead of its
+/// material's texture, see [`CamDisplay`](crate::cam_display::CamDisplay).
+pub struct DisplayTexture(pub Handle<Image>);
 
-        // bind group
-        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: BindingResource::TextureView(&gpu_image.texture_view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: BindingResource::Sampler(&gpu_image.sampler),
-                },
-                /*BindGroupEntry {
-                    binding: 2,
-                    resource: buffer.as_entire_binding(),
-                },*/
-            ],
+/// Render world component which blends this image over the display texture, in place of
+/// the material's secondary texture.
+pub struct DisplayBlend {
+    pub texture: Handle<Image>,
+    pub factor: f32,
+    pub transition: TransitionKind,
+}
+
+/// Render world component which shows a placeholder instead of the display's image,
+/// see [`NoSignal`](crate::cam_display::NoSignal).
+#[derive(Debug, Clone, Copy)]
+pub enum DisplayPlaceholder {
+    Color(Color),
+    /// Animated static.
+    Noise,
+}
+
+/// Render world component which samples the display's textures mirrored horizontally,
+/// see [`Mirror`](crate::mirror::Mirror).
+pub struct DisplayMirrored;
+
+#[derive(Clone, Default, AsStd140)]
+struct SSTDisplay {
+    blend_factor: f32,
+    transition: u32,
+    /// 0 shows the textures, 1 `placeholder_color` and 2 noise.
+    placeholder: u32,
+    /// 1 flips the textures horizontally.
+    mirrored: u32,
+    placeholder_color: Vec4,
+}
+
+/// Per display uniforms. Displays without a blend, placeholder or mirroring share the one at
+/// offset 0.
+#[derive(Default)]
+pub struct SSTDisplayUniforms {
+    uniforms: DynamicUniformVec<SSTDisplay>,
+}
+
+/// Bound in place of a display's image while it isn't ready, so that its placeholder can be drawn.
+/// Has a cube view for [`SamplingMode::Cubemap`] displays.
+pub struct SSTPlaceholderImage {
+    image: GpuImage,
+    cube_view: TextureView,
+}
+
+impl SSTPlaceholderImage {
+    fn texture_view(&self, sampling_mode: SamplingMode) -> &TextureView {
+        match sampling_mode {
+            SamplingMode::Cubemap => &self.cube_view,
+            SamplingMode::ScreenSpace | SamplingMode::MeshUv => &self.image.texture_view,
+        }
+    }
+}
+
+impl FromWorld for SSTPlaceholderImage {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+
+        // six layers, so that it can be viewed as a cube as well
+        let texture = render_device.create_texture(&TextureDescriptor {
             label: None,
-            layout: &custom_pipeline.material_layout,
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            usage: TextureUsage::SAMPLED,
+        });
+        let texture_view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2),
+            array_layer_count: NonZeroU32::new(1),
+            ..Default::default()
         });
+        let cube_view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::Cube),
+            array_layer_count: NonZeroU32::new(6),
+            ..Default::default()
+        });
+        let sampler = render_device.create_sampler(&SamplerDescriptor::default());
 
-        Ok(GpuScreenspaceTextureMaterial {
-            // _buffer: buffer,
-            bind_group,
-        })
+        SSTPlaceholderImage {
+            image: GpuImage {
+                texture,
+                texture_view,
+                sampler,
+            },
+            cube_view,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SSTDisplayUniformOffset(u32);
+
+type SSTBindGroupKey = (TextureViewId, TextureViewId, SamplerId, BufferId);
+
+/// Key into [`SSTBindGroups`], inserted on every render world entity queued by `queue_sst`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SSTBindGroupId(SSTBindGroupKey);
+
+/// Material bind groups by the texture views, sampler and display uniform buffer they bind.
+///
+/// Keeping them around means a bind group is only created when an image is (re)allocated,
+/// not when a [`DisplayTexture`] alternates between the two images of a [`RenderToTexture`](crate::render_to_texture::RenderToTexture).
+#[derive(Default)]
+pub struct SSTBindGroups {
+    frame: u64,
+    bind_groups: HashMap<SSTBindGroupKey, (BindGroup, u64)>,
+}
+impl SSTBindGroups {
+    fn get_or_create(
+        &mut self,
+        render_device: &RenderDevice,
+        layout: &BindGroupLayout,
+        texture_view: &TextureView,
+        secondary_texture_view: &TextureView,
+        sampler: &Sampler,
+        uniform_buffer: &Buffer,
+    ) -> SSTBindGroupId {
+        let key = (
+            texture_view.id(),
+            secondary_texture_view.id(),
+            sampler.id(),
+            uniform_buffer.id(),
+        );
+        let frame = self.frame;
+
+        let (_, last_used) = self.bind_groups.entry(key).or_insert_with(|| {
+            let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(secondary_texture_view),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: uniform_buffer,
+                            offset: 0,
+                            size: BufferSize::new(SSTDisplay::std140_size_static() as u64),
+                        }),
+                    },
+                ],
+                label: None,
+                layout,
+            });
+            (bind_group, frame)
+        });
+        *last_used = frame;
+
+        SSTBindGroupId(key)
+    }
+
+    pub fn get(&self, id: SSTBindGroupId) -> &BindGroup {
+        &self.bind_groups[&id.0].0
     }
+
+    /// Drops bind groups which weren't used in either of the last two frames.
+    fn next_frame(&mut self) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.bind_groups
+            .retain(|_, (_, last_used)| frame - *last_used <= 2);
+    }
+}
+
+/// Asset path of the display shader, relative to the `assets` folder.
+pub const SST_SHADER_PATH: &str = "custom.wgsl";
+
+/// Asset path of the fragment shader of [`SamplingMode::Cubemap`] displays, which binds cube
+/// textures where the display shader binds 2D ones.
+pub const SST_CUBEMAP_SHADER_PATH: &str = "cubemap.wgsl";
+
+/// Inserted before the [`ScreenspaceTexturePlugin`] is added to configure how the display
+/// shaders are loaded.
+#[derive(Debug, Clone, Default)]
+pub struct SSTShaderSettings {
+    /// Load the display shaders from [`SST_SHADER_PATH`] and [`SST_CUBEMAP_SHADER_PATH`] in the
+    /// app's `assets` folder instead of only using the copies embedded in the crate. With
+    /// `AssetServerSettings::watch_for_changes` and this crate's `hot_reload` feature, which
+    /// enables bevy's `filesystem_watcher`, edits to the files are picked up at runtime.
+    pub hot_reload: bool,
 }
 
+/// Handle to the display shader loaded from [`SST_SHADER_PATH`], only inserted with
+/// [`SSTShaderSettings::hot_reload`].
+///
+/// Until it is loaded, the copy of the shader embedded in the crate is used.
+pub struct SSTShaderHandle(pub Handle<Shader>);
+
+/// Handle to the cubemap fragment shader loaded from [`SST_CUBEMAP_SHADER_PATH`], loaded like
+/// the [`SSTShaderHandle`].
+pub struct SSTCubemapShaderHandle(pub Handle<Shader>);
+
 pub struct ScreenspaceTexturePlugin;
 
 impl Plugin for ScreenspaceTexturePlugin {
     fn build(&self, app: &mut App) {
+        let hot_reload = app
+            .world
+            .get_resource::<SSTShaderSettings>()
+            .map_or(false, |settings| settings.hot_reload);
+        if hot_reload {
+            let asset_server = app.world.get_resource::<AssetServer>().unwrap();
+            let shader = asset_server.load(SST_SHADER_PATH);
+            let cubemap_shader = asset_server.load(SST_CUBEMAP_SHADER_PATH);
+            app.insert_resource(SSTShaderHandle(shader))
+                .insert_resource(SSTCubemapShaderHandle(cubemap_shader));
+        }
+
         app.add_asset::<ScreenspaceTextureMaterial>()
             .add_plugin(ExtractComponentPlugin::<Handle<ScreenspaceTextureMaterial>>::default())
-            .add_plugin(RenderAssetPlugin::<ScreenspaceTextureMaterial>::default());
+            .add_plugin(RenderAssetPlugin::<ScreenspaceTextureMaterial>::default())
+            .init_resource::<SSTVertexLayouts>()
+            .add_system_to_stage(CoreStage::PostUpdate, update_sst_vertex_layouts)
+            .add_plugin(ViewSizePlugin);
         app.sub_app(RenderApp)
+            .add_system_to_stage(RenderStage::Extract, extract_sst_vertex_layouts)
+            .add_system_to_stage(RenderStage::Extract, extract_sst_shaders)
             .add_render_command::<Transparent3d, DrawScreenspaceTexture>()
+            .add_render_command::<Transparent3d, DrawScreenspaceTextureInstanced>()
             .init_resource::<SSTShaders>()
-            .init_resource::<SSTMeta>()
-            .init_resource::<ViewSizeUniforms>()
-            .add_system_to_stage(RenderStage::Prepare, prepare_view_sizes)
-            .add_system_to_stage(RenderStage::Queue, queue_view_sizes)
+            .init_resource::<SSTSamplers>()
+            .init_resource::<SSTBindGroups>()
+            .init_resource::<SSTInstanceBuffer>()
+            .init_resource::<SSTDisplayUniforms>()
+            .init_resource::<SSTPlaceholderImage>()
+            .init_resource::<ExtractedSSTShaders>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_sst_shaders)
             .add_system_to_stage(RenderStage::Queue, queue_sst);
     }
 }
 
 pub struct SSTShaders {
     material_layout: BindGroupLayout,
-    view_size_layout: BindGroupLayout,
-    pipeline: RenderPipeline,
+    cube_material_layout: BindGroupLayout,
+    pipeline_layout: PipelineLayout,
+    cube_pipeline_layout: PipelineLayout,
+    shader_module: ShaderModule,
+    cubemap_shader_module: ShaderModule,
+    custom_shaders: HashMap<Handle<Shader>, CustomShader>,
+    pipelines: Vec<RenderPipeline>,
+    pipeline_ids: HashMap<SSTPipelineKey, SSTPipelineId>,
 }
 
 impl FromWorld for SSTShaders {
@@ -132,126 +697,326 @@ impl FromWorld for SSTShaders {
         let render_device = world.get_resource::<RenderDevice>().unwrap();
         let shader = Shader::from_wgsl(include_str!("../assets/custom.wgsl"));
         let shader_module = render_device.create_shader_module(&shader);
+        let cubemap_shader = Shader::from_wgsl(include_str!("../assets/cubemap.wgsl"));
+        let cubemap_shader_module = render_device.create_shader_module(&cubemap_shader);
 
-        let material_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            entries: &[
-                // texture
-                BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStage::FRAGMENT,
-                    ty: BindingType::Texture {
-                        multisampled: false,
-                        sample_type: TextureSampleType::Float { filterable: true },
-                        view_dimension: TextureViewDimension::D2,
-                    },
-                    count: None,
-                },
-                // sampler
-                BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: ShaderStage::FRAGMENT,
-                    ty: BindingType::Sampler {
-                        comparison: false,
-                        filtering: true,
-                    },
-                    count: None,
-                },
-                // uniform data
-                /*BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: ShaderStage::FRAGMENT,
-                    ty: BindingType::Buffer {
-                        ty: BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: BufferSize::new(Vec4::std140_size_static() as u64),
-                    },
-                    count: None,
-                },*/
-            ],
-            label: None,
-        });
+        let material_layout = create_material_layout(render_device, TextureViewDimension::D2);
+        let cube_material_layout =
+            create_material_layout(render_device, TextureViewDimension::Cube);
 
-        let view_size_layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
-            label: None,
-            entries: &[BindGroupLayoutEntry {
+        let pbr_pipeline = world.get_resource::<PbrShaders>().unwrap();
+        let view_size_meta = world.get_resource::<ViewSizeMeta>().unwrap();
+
+        let create_pipeline_layout = |material_layout: &BindGroupLayout| {
+            render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: None,
+                push_constant_ranges: &[],
+                bind_group_layouts: &[
+                    &pbr_pipeline.view_layout,
+                    material_layout,
+                    &pbr_pipeline.mesh_layout,
+                    &view_size_meta.layout,
+                ],
+            })
+        };
+        let pipeline_layout = create_pipeline_layout(&material_layout);
+        let cube_pipeline_layout = create_pipeline_layout(&cube_material_layout);
+
+        SSTShaders {
+            material_layout,
+            cube_material_layout,
+            pipeline_layout,
+            cube_pipeline_layout,
+            shader_module,
+            cubemap_shader_module,
+            custom_shaders: HashMap::default(),
+            pipelines: Vec::new(),
+            pipeline_ids: HashMap::default(),
+        }
+    }
+}
+
+/// The layout of group 1, with the texture views having the given dimension.
+fn create_material_layout(
+    render_device: &RenderDevice,
+    view_dimension: TextureViewDimension,
+) -> BindGroupLayout {
+    render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        entries: &[
+            // texture
+            BindGroupLayoutEntry {
                 binding: 0,
                 visibility: ShaderStage::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension,
+                },
+                count: None,
+            },
+            // sampler
+            BindGroupLayoutEntry {
+                binding: 1,
+                visibility: ShaderStage::FRAGMENT,
+                ty: BindingType::Sampler {
+                    comparison: false,
+                    filtering: true,
+                },
+                count: None,
+            },
+            // secondary texture
+            BindGroupLayoutEntry {
+                binding: 2,
+                visibility: ShaderStage::FRAGMENT,
+                ty: BindingType::Texture {
+                    multisampled: false,
+                    sample_type: TextureSampleType::Float { filterable: true },
+                    view_dimension,
+                },
+                count: None,
+            },
+            // display uniform
+            BindGroupLayoutEntry {
+                binding: 3,
+                visibility: ShaderStage::FRAGMENT,
                 ty: BindingType::Buffer {
                     ty: BufferBindingType::Uniform,
                     has_dynamic_offset: true,
-                    min_binding_size: BufferSize::new(ViewSize::std140_size_static() as u64),
+                    min_binding_size: BufferSize::new(SSTDisplay::std140_size_static() as u64),
                 },
                 count: None,
-            }],
-        });
+            },
+        ],
+        label: None,
+    })
+}
 
-        let pbr_pipeline = world.get_resource::<PbrShaders>().unwrap();
+/// Everything a [`ScreenspaceTextureMaterial`] pipeline is specialized on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SSTPipelineKey {
+    pub sampling_mode: SamplingMode,
+    pub vertex_layout: SSTVertexLayout,
+    pub custom_shader: Option<Handle<Shader>>,
+    pub alpha_mode: AlphaMode,
+    pub cull_mode: Option<Face>,
+    pub depth_write_enabled: bool,
+    pub depth_compare: CompareFunction,
+    /// Whether the mesh transforms come from the per-instance vertex buffer of
+    /// [`SSTInstanceBuffer`] instead of the mesh uniform.
+    pub instanced: bool,
+}
 
-        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            push_constant_ranges: &[],
-            bind_group_layouts: &[
-                &pbr_pipeline.view_layout,
-                &material_layout,
-                &pbr_pipeline.mesh_layout,
-                &view_size_layout,
-            ],
+/// A user supplied fragment shader, compiled the first time a pipeline needs it.
+#[derive(Default)]
+struct CustomShader {
+    source: Option<Shader>,
+    module: Option<CompiledShader>,
+    /// The shader never compiled, pipelines use the default fragment shader until it does.
+    failed: bool,
+}
+
+/// Index into the specialized pipelines of [`SSTShaders`], inserted on every
+/// render world entity queued by `queue_sst`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SSTPipelineId(usize);
+
+impl SSTShaders {
+    /// Returns the pipeline for `key`, creating it on first use.
+    ///
+    /// Returns `None` if the key's custom shader isn't loaded yet. If it doesn't compile or lacks
+    /// the fragment entry point of the key's sampling mode, the default fragment shader is used
+    /// instead.
+    pub fn specialize(
+        &mut self,
+        render_device: &RenderDevice,
+        key: SSTPipelineKey,
+    ) -> Option<SSTPipelineId> {
+        if let Some(&id) = self.pipeline_ids.get(&key) {
+            return Some(id);
+        }
+
+        if let Some(handle) = &key.custom_shader {
+            let custom_shader = self.custom_shaders.get_mut(handle)?;
+            if let Some(source) = custom_shader.source.take() {
+                match compile_shader(render_device, &source, handle) {
+                    Some(compiled) => custom_shader.module = Some(compiled),
+                    None => {
+                        warn!(
+                            "using the default fragment shader instead of {:?}",
+                            handle.id
+                        );
+                        custom_shader.failed = true;
+                    }
+                }
+            }
+        }
+
+        let fragment_module = match &key.custom_shader {
+            Some(handle) => {
+                let custom_shader = self.custom_shaders.get(handle)?;
+                let entry_point = key.sampling_mode.fragment_entry_point();
+                match &custom_shader.module {
+                    Some(compiled) if compiled.has_entry_point(entry_point) => &compiled.module,
+                    Some(_) => {
+                        warn!(
+                            "shader {:?} has no `{}` entry point, using the default fragment shader",
+                            handle.id, entry_point
+                        );
+                        self.fragment_module(key.sampling_mode)
+                    }
+                    None if custom_shader.failed => self.fragment_module(key.sampling_mode),
+                    None => return None,
+                }
+            }
+            None => self.fragment_module(key.sampling_mode),
+        };
+
+        let pipeline = self.create_pipeline(render_device, &key, fragment_module);
+        let id = SSTPipelineId(self.pipelines.len());
+        self.pipelines.push(pipeline);
+        self.pipeline_ids.insert(key, id);
+        Some(id)
+    }
+
+    pub fn pipeline(&self, id: SSTPipelineId) -> &RenderPipeline {
+        &self.pipelines[id.0]
+    }
+
+    /// Replaces the shader module, dropping all pipelines specialized with the old one.
+    fn set_shader_module(&mut self, shader_module: ShaderModule) {
+        self.shader_module = shader_module;
+        self.clear_pipelines();
+    }
+
+    fn set_cubemap_shader_module(&mut self, shader_module: ShaderModule) {
+        self.cubemap_shader_module = shader_module;
+        self.clear_pipelines();
+    }
+
+    /// The module with the default fragment stage for `sampling_mode`.
+    fn fragment_module(&self, sampling_mode: SamplingMode) -> &ShaderModule {
+        match sampling_mode {
+            SamplingMode::Cubemap => &self.cubemap_shader_module,
+            SamplingMode::ScreenSpace | SamplingMode::MeshUv => &self.shader_module,
+        }
+    }
+
+    /// Stores the new source of a custom shader. If the shader is already in use it is
+    /// recompiled right away, otherwise once a pipeline needs it.
+    fn set_custom_shader(
+        &mut self,
+        render_device: &RenderDevice,
+        handle: Handle<Shader>,
+        shader: Shader,
+    ) {
+        let custom_shader = self.custom_shaders.entry(handle.clone()).or_default();
+        if custom_shader.module.is_none() && !custom_shader.failed {
+            custom_shader.source = Some(shader);
+            return;
+        }
+
+        // pipelines using the previous version or the fallback have to be rebuilt
+        if let Some(compiled) = compile_shader(render_device, &shader, &handle) {
+            custom_shader.module = Some(compiled);
+            custom_shader.failed = false;
+            self.clear_pipelines();
+        }
+    }
+
+    fn remove_custom_shader(&mut self, handle: &Handle<Shader>) {
+        if self.custom_shaders.remove(handle).is_some() {
+            self.clear_pipelines();
+        }
+    }
+
+    fn material_layout(&self, sampling_mode: SamplingMode) -> &BindGroupLayout {
+        match sampling_mode {
+            SamplingMode::Cubemap => &self.cube_material_layout,
+            _ => &self.material_layout,
+        }
+    }
+
+    fn clear_pipelines(&mut self) {
+        self.pipelines.clear();
+        self.pipeline_ids.clear();
+    }
+
+    fn create_pipeline(
+        &self,
+        render_device: &RenderDevice,
+        key: &SSTPipelineKey,
+        fragment_module: &ShaderModule,
+    ) -> RenderPipeline {
+        let mut attributes = vec![VertexAttribute {
+            format: VertexFormat::Float32x3,
+            offset: key.vertex_layout.position_offset,
+            shader_location: 0,
+        }];
+        if key.sampling_mode == SamplingMode::MeshUv {
+            attributes.push(VertexAttribute {
+                format: VertexFormat::Float32x2,
+                offset: key
+                    .vertex_layout
+                    .uv_offset
+                    .expect("MeshUv sampling requires a mesh with UVs"),
+                shader_location: 1,
+            });
+        }
+
+        // the columns of the instance's transform
+        let instance_attributes = [0, 1, 2, 3].map(|column| VertexAttribute {
+            format: VertexFormat::Float32x4,
+            offset: column * 16,
+            shader_location: 2 + column as u32,
         });
 
-        let pipeline = render_device.create_render_pipeline(&RenderPipelineDescriptor {
+        let mut buffers = vec![VertexBufferLayout {
+            array_stride: key.vertex_layout.array_stride,
+            step_mode: InputStepMode::Vertex,
+            attributes: &attributes,
+        }];
+        if key.instanced {
+            buffers.push(VertexBufferLayout {
+                array_stride: std::mem::size_of::<SSTInstance>() as u64,
+                step_mode: InputStepMode::Instance,
+                attributes: &instance_attributes,
+            });
+        }
+
+        render_device.create_render_pipeline(&RenderPipelineDescriptor {
             label: None,
             vertex: VertexState {
-                buffers: &[VertexBufferLayout {
-                    array_stride: 32,
-                    step_mode: InputStepMode::Vertex,
-                    attributes: &[
-                        // Position (GOTCHA! Vertex_Position isn't first in the buffer due to how Mesh sorts attributes (alphabetically))
-                        VertexAttribute {
-                            format: VertexFormat::Float32x3,
-                            offset: 12,
-                            shader_location: 0,
-                        },
-                        // Normal
-                        VertexAttribute {
-                            format: VertexFormat::Float32x3,
-                            offset: 0,
-                            shader_location: 1,
-                        },
-                        // Uv
-                        VertexAttribute {
-                            format: VertexFormat::Float32x2,
-                            offset: 24,
-                            shader_location: 2,
-                        },
-                    ],
-                }],
-                module: &shader_module,
-                entry_point: "vertex",
+                buffers: &buffers,
+                module: &self.shader_module,
+                entry_point: key.sampling_mode.vertex_entry_point(key.instanced),
             },
             fragment: Some(FragmentState {
-                module: &shader_module,
-                entry_point: "fragment",
+                module: fragment_module,
+                entry_point: key.sampling_mode.fragment_entry_point(),
                 targets: &[ColorTargetState {
                     format: TextureFormat::bevy_default(),
-                    blend: Some(BlendState {
-                        color: BlendComponent {
-                            src_factor: BlendFactor::SrcAlpha,
-                            dst_factor: BlendFactor::OneMinusSrcAlpha,
-                            operation: BlendOperation::Add,
-                        },
-                        alpha: BlendComponent {
-                            src_factor: BlendFactor::One,
-                            dst_factor: BlendFactor::One,
-                            operation: BlendOperation::Add,
-                        },
-                    }),
+                    blend: match key.alpha_mode {
+                        AlphaMode::Opaque => None,
+                        AlphaMode::Blend => Some(BlendState {
+                            color: BlendComponent {
+                                src_factor: BlendFactor::SrcAlpha,
+                                dst_factor: BlendFactor::OneMinusSrcAlpha,
+                                operation: BlendOperation::Add,
+                            },
+                            alpha: BlendComponent {
+                                src_factor: BlendFactor::One,
+                                dst_factor: BlendFactor::One,
+                                operation: BlendOperation::Add,
+                            },
+                        }),
+                    },
                     write_mask: ColorWrite::ALL,
                 }],
             }),
             depth_stencil: Some(DepthStencilState {
                 format: TextureFormat::Depth32Float,
-                depth_write_enabled: true,
-                depth_compare: CompareFunction::Greater,
+                depth_write_enabled: key.depth_write_enabled,
+                depth_compare: key.depth_compare,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
                     back: StencilFaceState::IGNORE,
@@ -264,117 +1029,538 @@ impl FromWorld for SSTShaders {
                     clamp: 0.0,
                 },
             }),
-            layout: Some(&pipeline_layout),
+            layout: Some(match key.sampling_mode {
+                SamplingMode::Cubemap => &self.cube_pipeline_layout,
+                _ => &self.pipeline_layout,
+            }),
             multisample: MultisampleState::default(),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleList,
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
-                cull_mode: Some(Face::Back),
+                cull_mode: key.cull_mode,
                 polygon_mode: PolygonMode::Fill,
                 clamp_depth: false,
                 conservative: false,
             },
-        });
+        })
+    }
+}
 
-        SSTShaders {
-            pipeline,
-            material_layout,
-            view_size_layout,
+/// A shader module and the names of its entry points, which pipelines can only use if they exist.
+struct CompiledShader {
+    module: ShaderModule,
+    entry_points: Vec<String>,
+}
+
+impl CompiledShader {
+    fn has_entry_point(&self, name: &str) -> bool {
+        self.entry_points
+            .iter()
+            .any(|entry_point| entry_point == name)
+    }
+
+    /// Keeps the shader only if it has all of `entry_points`, for shaders every pipeline uses.
+    fn require_entry_points(
+        self,
+        handle: &Handle<Shader>,
+        entry_points: &[&str],
+    ) -> Option<CompiledShader> {
+        let missing: Vec<&str> = entry_points
+            .iter()
+            .copied()
+            .filter(|name| !self.has_entry_point(name))
+            .collect();
+        if missing.is_empty() {
+            Some(self)
+        } else {
+            error!(
+                "shader {:?} lacks the entry points {:?}",
+                handle.id, missing
+            );
+            None
         }
     }
 }
 
-#[derive(Default)]
-struct SSTMeta {
-    view_size_bind_group: Option<BindGroup>,
+/// Entry points of the display shader at [`SST_SHADER_PATH`], which has the vertex stage of every
+/// pipeline.
+const SST_SHADER_ENTRY_POINTS: &[&str] = &[
+    "vertex",
+    "vertex_instanced",
+    "vertex_uv",
+    "vertex_uv_instanced",
+    "fragment",
+    "fragment_mesh_uv",
+];
+
+/// Entry points of the cubemap fragment shader at [`SST_CUBEMAP_SHADER_PATH`].
+const SST_CUBEMAP_SHADER_ENTRY_POINTS: &[&str] = &["fragment_cubemap"];
+
+/// Whether the pipeline layout has the binding. Groups 0 and 2 are the view and mesh layouts of
+/// the PBR pipeline, groups 1 and 3 are the material and [`ViewSizeMeta`] layouts.
+fn is_in_layout(group: u32, binding: u32) -> bool {
+    match group {
+        0 | 2 => true,
+        1 => binding <= 3,
+        3 => binding == 0,
+        _ => false,
+    }
 }
 
-#[derive(AsStd140)]
-struct ViewSize {
-    size: Vec2,
+/// Validates `shader` and its bindings, which wgpu would panic on, and returns the names of its
+/// entry points.
+fn shader_entry_points(shader: &Shader, handle: &Handle<Shader>) -> Option<Vec<String>> {
+    let reflection = match shader.reflect() {
+        Ok(reflection) => reflection,
+        Err(e) => {
+            error!("failed to compile shader {:?}: {:?}", handle.id, e);
+            return None;
+        }
+    };
+
+    for (_, global) in reflection.module.global_variables.iter() {
+        if let Some(binding) = &global.binding {
+            if !is_in_layout(binding.group, binding.binding) {
+                error!(
+                    "shader {:?} uses group {} binding {}, which displays don't bind",
+                    handle.id, binding.group, binding.binding
+                );
+                return None;
+            }
+        }
+    }
+
+    Some(
+        reflection
+            .module
+            .entry_points
+            .iter()
+            .map(|entry_point| entry_point.name.clone())
+            .collect(),
+    )
 }
-struct ViewSizeUniformOffset(u32);
 
-#[derive(Default)]
-struct ViewSizeUniforms {
-    uniforms: DynamicUniformVec<ViewSize>,
+/// Validates `shader` before handing it to wgpu, which would panic on errors.
+fn compile_shader(
+    render_device: &RenderDevice,
+    shader: &Shader,
+    handle: &Handle<Shader>,
+) -> Option<CompiledShader> {
+    let entry_points = shader_entry_points(shader, handle)?;
+    Some(CompiledShader {
+        module: render_device.create_shader_module(shader),
+        entry_points,
+    })
 }
-impl ViewSizeUniforms {
-    fn push(&mut self, value: ViewSize) -> ViewSizeUniformOffset {
-        let offset = self.uniforms.push(value);
-        ViewSizeUniformOffset(offset)
-    }
+
+#[derive(Default)]
+struct ExtractedSSTShaders {
+    default_shader: Option<(Handle<Shader>, Shader)>,
+    cubemap_shader: Option<(Handle<Shader>, Shader)>,
+    changed: Vec<(Handle<Shader>, Shader)>,
+    removed: Vec<Handle<Shader>>,
 }
 
-fn prepare_view_sizes(
+/// Extracts the default shaders and the custom shaders of the materials. Other shaders, like
+/// those of the PBR pipeline, don't affect the display pipelines and are left alone.
+fn extract_sst_shaders(
     mut commands: Commands,
-    render_device: Res<RenderDevice>,
-    render_queue: Res<RenderQueue>,
-    mut view_size_uniforms: ResMut<ViewSizeUniforms>,
-    extracted_views: Query<(Entity, &ExtractedView)>,
+    mut shader_events: EventReader<AssetEvent<Shader>>,
+    shaders: Res<Assets<Shader>>,
+    materials: Res<Assets<ScreenspaceTextureMaterial>>,
+    // only there with `SSTShaderSettings::hot_reload`
+    shader_handle: Option<Res<SSTShaderHandle>>,
+    cubemap_shader_handle: Option<Res<SSTCubemapShaderHandle>>,
+    mut extracted_handles: Local<HashSet<Handle<Shader>>>,
 ) {
-    view_size_uniforms
-        .uniforms
-        .reserve_and_clear(extracted_views.iter().len(), &render_device);
+    let mut extracted = ExtractedSSTShaders::default();
+    let shader_handle = shader_handle.map(|shader_handle| shader_handle.0.clone_weak());
+    let cubemap_shader_handle =
+        cubemap_shader_handle.map(|shader_handle| shader_handle.0.clone_weak());
+    let used: HashSet<&Handle<Shader>> = materials
+        .iter()
+        .filter_map(|(_, material)| material.custom_shader.as_ref())
+        .collect();
 
-    for (entity, view) in extracted_views.iter() {
-        let offset = view_size_uniforms.push(ViewSize {
-            size: Vec2::new(view.width as f32, view.height as f32),
-        });
+    for event in shader_events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if shader_handle.as_ref() == Some(handle) {
+                    if let Some(shader) = shaders.get(handle) {
+                        extracted.default_shader = Some((handle.clone_weak(), shader.clone()));
+                    }
+                } else if cubemap_shader_handle.as_ref() == Some(handle) {
+                    if let Some(shader) = shaders.get(handle) {
+                        extracted.cubemap_shader = Some((handle.clone_weak(), shader.clone()));
+                    }
+                } else if extracted_handles.contains(handle) {
+                    // new versions of shaders that were in use once
+                    if let Some(shader) = shaders.get(handle) {
+                        extracted
+                            .changed
+                            .push((handle.clone_weak(), shader.clone()));
+                    }
+                }
+            }
+            AssetEvent::Removed { handle } => {
+                if extracted_handles.remove(handle) {
+                    extracted.removed.push(handle.clone_weak());
+                }
+            }
+        }
+    }
 
-        commands.entity(entity).insert(offset);
+    // shaders that just got loaded or referenced by a material
+    for &handle in used.iter() {
+        if extracted_handles.contains(handle) {
+            continue;
+        }
+        if let Some(shader) = shaders.get(handle) {
+            extracted
+                .changed
+                .push((handle.clone_weak(), shader.clone()));
+            extracted_handles.insert(handle.clone_weak());
+        }
     }
 
-    view_size_uniforms.uniforms.write_buffer(&render_queue);
+    commands.insert_resource(extracted);
 }
 
-fn queue_view_sizes(
-    mut custom_shader_meta: ResMut<SSTMeta>,
-    custom_shaders: Res<SSTShaders>,
-    view_size_uniforms: Res<ViewSizeUniforms>,
+fn prepare_sst_shaders(
+    mut extracted_shaders: ResMut<ExtractedSSTShaders>,
     render_device: Res<RenderDevice>,
+    mut custom_shaders: ResMut<SSTShaders>,
 ) {
-    let view_size_binding = match view_size_uniforms.uniforms.binding() {
-        Some(val) => val,
-        None => return,
-    };
+    // edits that would break any pipeline keep the last good module
+    if let Some((handle, shader)) = extracted_shaders.default_shader.take() {
+        if let Some(compiled) = compile_shader(&render_device, &shader, &handle)
+            .and_then(|compiled| compiled.require_entry_points(&handle, SST_SHADER_ENTRY_POINTS))
+        {
+            custom_shaders.set_shader_module(compiled.module);
+        }
+    }
+    if let Some((handle, shader)) = extracted_shaders.cubemap_shader.take() {
+        if let Some(compiled) =
+            compile_shader(&render_device, &shader, &handle).and_then(|compiled| {
+                compiled.require_entry_points(&handle, SST_CUBEMAP_SHADER_ENTRY_POINTS)
+            })
+        {
+            custom_shaders.set_cubemap_shader_module(compiled.module);
+        }
+    }
 
-    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
-        label: None,
-        layout: &custom_shaders.view_size_layout,
-        entries: &[BindGroupEntry {
-            binding: 0,
-            resource: view_size_binding,
-        }],
-    });
-    custom_shader_meta.view_size_bind_group = Some(bind_group);
+    for (handle, shader) in extracted_shaders.changed.drain(..) {
+        custom_shaders.set_custom_shader(&render_device, handle, shader);
+    }
+    for handle in extracted_shaders.removed.drain(..) {
+        custom_shaders.remove_custom_shader(&handle);
+    }
+}
+
+/// A column-major mesh transform, read by the instanced vertex entry points.
+type SSTInstance = [[f32; 4]; 4];
+
+/// Per-instance transforms of all batched displays.
+pub struct SSTInstanceBuffer {
+    instances: BufferVec<SSTInstance>,
+    len: u32,
+}
+impl Default for SSTInstanceBuffer {
+    fn default() -> Self {
+        SSTInstanceBuffer {
+            instances: BufferVec::new(BufferUsage::VERTEX),
+            len: 0,
+        }
+    }
 }
 
+/// The range of [`SSTInstanceBuffer`] drawn by an instanced batch.
+pub struct SSTInstances(Range<u32>);
+
+struct SSTBatch {
+    key: SSTPipelineKey,
+    bind_group_id: SSTBindGroupId,
+    uniform_offset: SSTDisplayUniformOffset,
+    instances: Vec<(Entity, Mat4)>,
+}
+
+/// The view of `gpu_image` bound for `sampling_mode`, which for cubemaps is a cube view.
+fn bound_texture_view<'a>(
+    sampling_mode: SamplingMode,
+    gpu_image: &'a GpuImage,
+    cubemap_views: Option<&'a CubemapViews>,
+) -> Option<&'a TextureView> {
+    match sampling_mode {
+        SamplingMode::Cubemap => cubemap_views?.cube_view(gpu_image),
+        SamplingMode::ScreenSpace | SamplingMode::MeshUv => Some(&gpu_image.texture_view),
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn queue_sst(
+    mut commands: Commands,
     transparent_3d_draw_functions: Res<DrawFunctions<Transparent3d>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut instance_buffer: ResMut<SSTInstanceBuffer>,
+    mut display_uniforms: ResMut<SSTDisplayUniforms>,
+    placeholder_image: Res<SSTPlaceholderImage>,
+    mut custom_shaders: ResMut<SSTShaders>,
+    mut samplers: ResMut<SSTSamplers>,
+    mut bind_groups: ResMut<SSTBindGroups>,
     materials: Res<RenderAssets<ScreenspaceTextureMaterial>>,
-    material_meshes: Query<
-        (Entity, &Handle<ScreenspaceTextureMaterial>, &MeshUniform),
-        With<Handle<Mesh>>,
-    >,
+    gpu_images: Res<RenderAssets<Image>>,
+    gpu_meshes: Res<RenderAssets<Mesh>>,
+    // only there with the `RenderToCubemapPlugin`
+    cubemap_views: Option<Res<CubemapViews>>,
+    material_meshes: Query<(
+        Entity,
+        &Handle<ScreenspaceTextureMaterial>,
+        Option<&DisplayTexture>,
+        Option<&DisplayBlend>,
+        Option<&DisplayPlaceholder>,
+        Option<&DisplayMirrored>,
+        &Handle<Mesh>,
+        &SSTVertexLayout,
+        &MeshUniform,
+    )>,
     mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+    mut meshes_without_uvs: Local<HashSet<Handle<Mesh>>>,
 ) {
-    let draw_custom = transparent_3d_draw_functions
-        .read()
-        .get_id::<DrawScreenspaceTexture>()
+    let draw_functions = transparent_3d_draw_functions.read();
+    let draw_custom = draw_functions.get_id::<DrawScreenspaceTexture>().unwrap();
+    let draw_instanced = draw_functions
+        .get_id::<DrawScreenspaceTextureInstanced>()
         .unwrap();
+
+    bind_groups.next_frame();
+
+    display_uniforms
+        .uniforms
+        .reserve_and_clear(material_meshes.iter().len() + 1, &render_device);
+    let default_uniform =
+        SSTDisplayUniformOffset(display_uniforms.uniforms.push(SSTDisplay::default()));
+
+    // opaque entities with the same pipeline, mesh, bind group and display uniform are drawn in
+    // one instanced draw call. Blended ones are sorted one by one, so they can't be batched, and
+    // the instanced draw needs an index buffer.
+    let mut batches = HashMap::default();
+    for (
+        entity,
+        material_handle,
+        display_texture,
+        display_blend,
+        display_placeholder,
+        display_mirrored,
+        mesh_handle,
+        vertex_layout,
+        mesh_uniform,
+    ) in material_meshes.iter()
+    {
+        let material = match materials.get(material_handle) {
+            Some(material) => material,
+            None => continue,
+        };
+
+        let texture = match display_texture {
+            Some(display_texture) => &display_texture.0,
+            None => &material.texture,
+        };
+        let bound_image = gpu_images.get(texture).and_then(|gpu_image| {
+            let texture_view =
+                bound_texture_view(material.sampling_mode, gpu_image, cubemap_views.as_deref())?;
+            Some((gpu_image, texture_view))
+        });
+        // displays with a placeholder are drawn even if their image isn't ready
+        let (gpu_image, texture_view) = match (bound_image, display_placeholder) {
+            (Some(bound_image), _) => bound_image,
+            (None, Some(_)) => (
+                &placeholder_image.image,
+                placeholder_image.texture_view(material.sampling_mode),
+            ),
+            (None, None) => continue,
+        };
+        let (secondary_texture, blend_factor, transition) = match display_blend {
+            Some(display_blend) => (
+                Some(&display_blend.texture),
+                display_blend.factor,
+                display_blend.transition,
+            ),
+            None => (
+                material.secondary_texture.as_ref(),
+                material.blend_factor,
+                material.transition,
+            ),
+        };
+        let secondary_texture_view = secondary_texture
+            .filter(|_| blend_factor > 0.0)
+            .and_then(|texture| gpu_images.get(texture))
+            .and_then(|secondary_gpu_image| {
+                bound_texture_view(
+                    material.sampling_mode,
+                    secondary_gpu_image,
+                    cubemap_views.as_deref(),
+                )
+            });
+
+        let mut display = SSTDisplay::default();
+        if secondary_texture_view.is_some() {
+            display.blend_factor = blend_factor;
+            display.transition = transition as u32;
+        }
+        match display_placeholder {
+            Some(&DisplayPlaceholder::Color(color)) => {
+                display.placeholder = 1;
+                display.placeholder_color = color.as_linear_rgba_f32().into();
+            }
+            Some(DisplayPlaceholder::Noise) => display.placeholder = 2,
+            None => {}
+        }
+        if display_mirrored.is_some() {
+            display.mirrored = 1;
+        }
+        let uniform_offset = if secondary_texture_view.is_some()
+            || display_placeholder.is_some()
+            || display_mirrored.is_some()
+        {
+            SSTDisplayUniformOffset(display_uniforms.uniforms.push(display))
+        } else {
+            default_uniform
+        };
+
+        let sampler = match material.sampler {
+            Some(settings) => samplers.get_or_create(&render_device, settings),
+            None => &gpu_image.sampler,
+        };
+
+        if material.sampling_mode == SamplingMode::MeshUv && vertex_layout.uv_offset.is_none() {
+            if meshes_without_uvs.insert(mesh_handle.clone_weak()) {
+                warn!(
+                    "mesh {:?} has no `{}` attribute required by SamplingMode::MeshUv",
+                    mesh_handle.id,
+                    Mesh::ATTRIBUTE_UV_0
+                );
+            }
+            continue;
+        }
+
+        let key = SSTPipelineKey {
+            sampling_mode: material.sampling_mode,
+            vertex_layout: vertex_layout.clone(),
+            custom_shader: material.custom_shader.clone(),
+            alpha_mode: material.alpha_mode,
+            cull_mode: material.cull_mode,
+            depth_write_enabled: material.depth_write_enabled,
+            depth_compare: material.depth_compare,
+            instanced: false,
+        };
+        let pipeline_id = match custom_shaders.specialize(&render_device, key.clone()) {
+            Some(pipeline_id) => pipeline_id,
+            None => continue,
+        };
+        let bind_group_id = bind_groups.get_or_create(
+            &render_device,
+            custom_shaders.material_layout(material.sampling_mode),
+            texture_view,
+            secondary_texture_view.unwrap_or(texture_view),
+            sampler,
+            display_uniforms.uniforms.uniform_buffer().unwrap(),
+        );
+
+        let batchable = material.alpha_mode == AlphaMode::Opaque
+            && gpu_meshes
+                .get(mesh_handle)
+                .map_or(false, |gpu_mesh| gpu_mesh.index_info.is_some());
+        let unbatched_entity = Some(entity).filter(|_| !batchable);
+
+        batches
+            .entry((
+                pipeline_id,
+                mesh_handle.clone_weak(),
+                bind_group_id,
+                uniform_offset,
+                unbatched_entity,
+            ))
+            .or_insert_with(|| SSTBatch {
+                key,
+                bind_group_id,
+                uniform_offset,
+                instances: Vec::new(),
+            })
+            .instances
+            .push((entity, mesh_uniform.transform));
+    }
+
+    let instance_count = batches
+        .values()
+        .map(|batch| batch.instances.len())
+        .filter(|&len| len > 1)
+        .sum();
+    instance_buffer
+        .instances
+        .reserve_and_clear(instance_count, &render_device);
+    instance_buffer.len = 0;
+
+    let mut queued = Vec::new();
+    for ((pipeline_id, ..), batch) in batches {
+        let alpha_mode = batch.key.alpha_mode;
+
+        if let [(entity, transform)] = batch.instances[..] {
+            commands.entity(entity).insert_bundle((
+                pipeline_id,
+                batch.bind_group_id,
+                batch.uniform_offset,
+            ));
+
+            queued.push((entity, draw_custom, alpha_mode, transform.col(3)));
+            continue;
+        }
+
+        let key = SSTPipelineKey {
+            instanced: true,
+            ..batch.key
+        };
+        let pipeline_id = match custom_shaders.specialize(&render_device, key) {
+            Some(pipeline_id) => pipeline_id,
+            None => continue,
+        };
+
+        let start = instance_buffer.len;
+        for &(_, transform) in &batch.instances {
+            instance_buffer.instances.push(transform.to_cols_array_2d());
+        }
+        instance_buffer.len += batch.instances.len() as u32;
+
+        // the batch is drawn through its first entity. Being opaque, it isn't sorted by distance.
+        let (entity, transform) = batch.instances[0];
+        commands.entity(entity).insert_bundle((
+            pipeline_id,
+            batch.bind_group_id,
+            batch.uniform_offset,
+            SSTInstances(start..instance_buffer.len),
+        ));
+
+        queued.push((entity, draw_instanced, alpha_mode, transform.col(3)));
+    }
+
+    instance_buffer.instances.write_buffer(&render_queue);
+    display_uniforms.uniforms.write_buffer(&render_queue);
+
     for (view, mut transparent_phase) in views.iter_mut() {
         let view_matrix = view.transform.compute_matrix();
         let view_row_2 = view_matrix.row(2);
-        for (entity, material_handle, mesh_uniform) in material_meshes.iter() {
-            if materials.contains_key(material_handle) {
-                transparent_phase.add(Transparent3d {
-                    entity,
-                    draw_function: draw_custom,
-                    distance: view_row_2.dot(mesh_uniform.transform.col(3)),
-                });
-            }
+        for &(entity, draw_function, alpha_mode, position) in &queued {
+            let distance = match alpha_mode {
+                // sort opaque displays before everything else in the phase
+                AlphaMode::Opaque => f32::NEG_INFINITY,
+                AlphaMode::Blend => view_row_2.dot(position),
+            };
+            transparent_phase.add(Transparent3d {
+                entity,
+                draw_function,
+                distance,
+            });
         }
     }
 }
@@ -383,46 +1569,207 @@ type DrawScreenspaceTexture = (
     SetCustomMaterialPipeline,
     SetMeshViewBindGroup<0>,
     SetTransformBindGroup<2>,
-    SetViewSizesBindGroup<3>,
+    SetViewSizeBindGroup<3>,
     DrawMesh,
 );
 
+/// The pipeline layout still contains the mesh uniform, so it is bound even though
+/// the instanced shader reads its transforms from the instance buffer.
+type DrawScreenspaceTextureInstanced = (
+    SetCustomMaterialPipeline,
+    SetMeshViewBindGroup<0>,
+    SetTransformBindGroup<2>,
+    SetViewSizeBindGroup<3>,
+    DrawMeshInstanced,
+);
+
 struct SetCustomMaterialPipeline;
 
 impl RenderCommand<Transparent3d> for SetCustomMaterialPipeline {
     type Param = (
-        SRes<RenderAssets<ScreenspaceTextureMaterial>>,
         SRes<SSTShaders>,
-        SQuery<Read<Handle<ScreenspaceTextureMaterial>>>,
+        SRes<SSTBindGroups>,
+        SQuery<(
+            Read<SSTPipelineId>,
+            Read<SSTBindGroupId>,
+            Read<SSTDisplayUniformOffset>,
+        )>,
     );
     fn render<'w>(
         _view: Entity,
         item: &Transparent3d,
-        (materials, custom_pipeline, query): SystemParamItem<'w, '_, Self::Param>,
+        (custom_pipeline, bind_groups, query): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) {
-        let material_handle = query.get(item.entity).unwrap();
-        let material = materials.into_inner().get(material_handle).unwrap();
+        let (&pipeline_id, &bind_group_id, uniform_offset) = query.get(item.entity).unwrap();
 
-        pass.set_render_pipeline(&custom_pipeline.into_inner().pipeline);
-        pass.set_bind_group(1, &material.bind_group, &[]);
+        pass.set_render_pipeline(custom_pipeline.into_inner().pipeline(pipeline_id));
+        pass.set_bind_group(
+            1,
+            bind_groups.into_inner().get(bind_group_id),
+            &[uniform_offset.0],
+        );
     }
 }
 
-struct SetViewSizesBindGroup<const I: usize>;
+struct DrawMeshInstanced;
 
-impl<const I: usize> RenderCommand<Transparent3d> for SetViewSizesBindGroup<I> {
-    type Param = (SRes<SSTMeta>, SQuery<Read<ViewSizeUniformOffset>>);
+impl RenderCommand<Transparent3d> for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<Mesh>>,
+        SRes<SSTInstanceBuffer>,
+        SQuery<(Read<Handle<Mesh>>, Read<SSTInstances>)>,
+    );
 
     fn render<'w>(
-        view: Entity,
-        _: &Transparent3d,
-        (meta, view_size_offsets): SystemParamItem<'w, '_, Self::Param>,
+        _view: Entity,
+        item: &Transparent3d,
+        (meshes, instance_buffer, query): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) {
-        let view_size_offset = view_size_offsets.get(view).unwrap();
-        let view_size_bind_group = meta.into_inner().view_size_bind_group.as_ref().unwrap();
+        let (mesh_handle, instances) = query.get(item.entity).unwrap();
+        let gpu_mesh = meshes.into_inner().get(mesh_handle).unwrap();
+        let instance_buffer = instance_buffer.into_inner().instances.buffer().unwrap();
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        if let Some(index_info) = &gpu_mesh.index_info {
+            pass.set_index_buffer(index_info.buffer.slice(..), 0, index_info.index_format);
+            pass.draw_indexed(0..index_info.count, 0, instances.0.clone());
+        } else {
+            unreachable!("meshes without indices aren't batched")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anisotropy_clamp(settings: SamplerSettings, clamp: u8) -> Option<u8> {
+        SamplerSettings {
+            anisotropy_clamp: NonZeroU8::new(clamp),
+            ..settings
+        }
+        .valid_anisotropy_clamp()
+        .map(NonZeroU8::get)
+    }
+
+    #[test]
+    fn anisotropy_clamp_is_a_power_of_two() {
+        let linear = SamplerSettings::default();
+        assert_eq!(anisotropy_clamp(linear, 0), None);
+        assert_eq!(anisotropy_clamp(linear, 1), Some(1));
+        assert_eq!(anisotropy_clamp(linear, 3), Some(2));
+        assert_eq!(anisotropy_clamp(linear, 8), Some(8));
+        assert_eq!(anisotropy_clamp(linear, 15), Some(8));
+        assert_eq!(anisotropy_clamp(linear, 16), Some(16));
+        assert_eq!(anisotropy_clamp(linear, 255), Some(16));
+    }
 
-        pass.set_bind_group(I, view_size_bind_group, &[view_size_offset.0]);
+    #[test]
+    fn anisotropy_clamp_requires_linear_filters() {
+        assert_eq!(anisotropy_clamp(SamplerSettings::nearest(), 4), None);
+        let nearest_mipmaps = SamplerSettings {
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        };
+        assert_eq!(anisotropy_clamp(nearest_mipmaps, 4), None);
+    }
+
+    fn mesh(attributes: &[&'static str]) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        for &name in attributes {
+            if name == Mesh::ATTRIBUTE_UV_0 {
+                mesh.set_attribute(name, vec![[0.0f32; 2]; 3]);
+            } else {
+                mesh.set_attribute(name, vec![[0.0f32; 3]; 3]);
+            }
+        }
+        mesh
+    }
+
+    #[test]
+    fn vertex_layout_without_uv() {
+        let mesh = mesh(&[Mesh::ATTRIBUTE_POSITION, Mesh::ATTRIBUTE_NORMAL]);
+        let layout = SSTVertexLayout::from_mesh(&mesh).unwrap();
+        assert_eq!(
+            layout,
+            SSTVertexLayout {
+                array_stride: 24,
+                position_offset: 12,
+                uv_offset: None,
+            }
+        );
+    }
+
+    #[test]
+    fn vertex_layout_without_normal() {
+        let mesh = mesh(&[Mesh::ATTRIBUTE_POSITION, Mesh::ATTRIBUTE_UV_0]);
+        let layout = SSTVertexLayout::from_mesh(&mesh).unwrap();
+        assert_eq!(
+            layout,
+            SSTVertexLayout {
+                array_stride: 20,
+                position_offset: 0,
+                uv_offset: Some(12),
+            }
+        );
+    }
+
+    #[test]
+    fn vertex_layout_without_position() {
+        let mesh = mesh(&[Mesh::ATTRIBUTE_NORMAL, Mesh::ATTRIBUTE_UV_0]);
+        assert_eq!(
+            SSTVertexLayout::from_mesh(&mesh),
+            Err(SSTVertexLayoutError::MissingAttribute(
+                Mesh::ATTRIBUTE_POSITION
+            ))
+        );
+    }
+
+    #[test]
+    fn custom_shader_entry_points() {
+        let shader = Shader::from_wgsl(include_str!("../assets/swirl.wgsl"));
+        let entry_points = shader_entry_points(&shader, &Handle::default()).unwrap();
+        assert!(entry_points.iter().any(|name| name == "fragment"));
+        assert!(entry_points.iter().any(|name| name == "fragment_mesh_uv"));
+        assert!(!entry_points.iter().any(|name| name == "fragment_cubemap"));
+    }
+
+    #[test]
+    fn shaders_with_unbound_resources_are_rejected() {
+        let shader = Shader::from_wgsl(
+            r#"
+[[group(1), binding(7)]]
+var texture: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fragment() -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+"#,
+        );
+        assert_eq!(shader_entry_points(&shader, &Handle::default()), None);
+    }
+
+    #[test]
+    fn default_shaders_have_every_entry_point() {
+        for (source, entry_points) in [
+            (
+                include_str!("../assets/custom.wgsl"),
+                SST_SHADER_ENTRY_POINTS,
+            ),
+            (
+                include_str!("../assets/cubemap.wgsl"),
+                SST_CUBEMAP_SHADER_ENTRY_POINTS,
+            ),
+        ] {
+            let shader = Shader::from_wgsl(source);
+            let entry_points_found = shader_entry_points(&shader, &Handle::default()).unwrap();
+            for entry_point in entry_points {
+                assert!(entry_points_found.contains(&entry_point.to_string()));
+            }
+        }
     }
 }
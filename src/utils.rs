@@ -1,81 +1,432 @@
-use bevy::input::mouse::MouseMotion;
+use std::path::Path;
+
+use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
-use bevy::window::WindowFocused;
+use bevy::window::{WindowFocused, WindowId};
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
 
 pub struct FlycamPlugin;
 impl Plugin for FlycamPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<FlycamOptions>()
-            .add_system(camera_movement)
-            .add_system(camera_look)
+            .register_type::<Flycam>()
+            .add_system(init_flycams.label(FlycamSystem::Init))
+            .add_system(cycle_active_flycam.after(FlycamSystem::Init))
+            .add_system(switch_flycam_mode.after(FlycamSystem::Init))
+            .add_system(
+                camera_look
+                    .label(FlycamSystem::Look)
+                    .after(FlycamSystem::Init),
+            )
+            .add_system(camera_movement.after(FlycamSystem::Look))
             .add_system(toggle_cursor)
             .add_system(toggle_cursor_manually);
     }
 }
 
+#[derive(SystemLabel, Clone, Debug, Hash, PartialEq, Eq)]
+enum FlycamSystem {
+    Init,
+    Look,
+}
+
+/// Settings of the flycams, which can be loaded from and saved to a RON config file.
+/// Whether they are `enabled` isn't saved.
+#[derive(Reflect, Inspectable, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FlycamOptions {
-    pub yaw: f32,
-    pub pitch: f32,
-    pub sensitivity: f32,
+    /// Degrees turned per pixel of mouse motion, independent of the frame rate.
+    #[inspectable(min = 0.0)]
+    pub degrees_per_pixel: f32,
+    /// Movement speed in units per second.
+    #[inspectable(min = 0.0)]
+    pub speed: f32,
+    /// Movement speed while `bindings.boost` is held.
+    #[inspectable(min = 0.0)]
+    pub boost_speed: f32,
+    /// Moving the mouse up looks down.
+    pub invert_y: bool,
+    /// Time in seconds the rotation takes to cover ~63% of the way to where the mouse points.
+    /// 0 turns instantly.
+    #[inspectable(min = 0.0)]
+    pub look_smoothing: f32,
+    /// Time in seconds the velocity takes to cover ~63% of the way to the movement speed while
+    /// a movement key is held. 0 starts moving instantly.
+    #[inspectable(min = 0.0)]
+    pub acceleration_time: f32,
+    /// Like `acceleration_time`, but for slowing down once no movement key is held.
+    #[inspectable(min = 0.0)]
+    pub damping_time: f32,
+    /// Distance to the focus when switching to [`FlycamMode::Orbit`].
+    #[inspectable(min = 0.0)]
+    pub orbit_distance: f32,
+    /// How much one step of the mouse wheel zooms in orbit mode.
+    #[inspectable(min = 0.0)]
+    pub zoom_sensitivity: f32,
+    /// Height of the ground plane in walk mode.
+    pub ground_height: f32,
+    /// Height of the camera above the ground in walk mode. Switching to walk mode above it makes
+    /// the camera fall down to it, and below it snaps the camera up.
+    #[inspectable(min = 0.0)]
+    pub eye_height: f32,
+    /// In units per second squared.
+    #[inspectable(min = 0.0)]
+    pub gravity: f32,
+    /// Upwards speed when jumping with `bindings.up` in walk mode.
+    #[inspectable(min = 0.0)]
+    pub jump_speed: f32,
+    #[reflect(ignore)]
+    #[inspectable(ignore)]
+    pub bindings: FlycamBindings,
+    #[serde(skip)]
     pub enabled: bool,
 }
 impl Default for FlycamOptions {
     fn default() -> Self {
         Self {
-            yaw: Default::default(),
-            pitch: Default::default(),
-            sensitivity: 3.0,
+            degrees_per_pixel: 0.05,
+            speed: 5.0,
+            boost_speed: 20.0,
+            invert_y: false,
+            look_smoothing: 0.0,
+            acceleration_time: 0.0,
+            damping_time: 0.0,
+            orbit_distance: 10.0,
+            zoom_sensitivity: 0.1,
+            ground_height: 0.0,
+            eye_height: 1.7,
+            gravity: 9.81,
+            jump_speed: 4.0,
+            bindings: Default::default(),
             enabled: true,
         }
     }
 }
+impl FlycamOptions {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, FlycamConfigError> {
+        let config = std::fs::read_to_string(path).map_err(FlycamConfigError::Io)?;
+        ron::de::from_str(&config).map_err(FlycamConfigError::Ron)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), FlycamConfigError> {
+        let config = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())
+            .map_err(FlycamConfigError::Ron)?;
+        std::fs::write(path, config).map_err(FlycamConfigError::Io)
+    }
+}
+
+/// The keys controlling the flycam.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlycamBindings {
+    pub forward: KeyCode,
+    pub back: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub up: KeyCode,
+    pub down: KeyCode,
+    /// Held to move at `boost_speed`.
+    pub boost: KeyCode,
+    /// Toggles the flycam and the cursor grab.
+    pub toggle: KeyCode,
+    /// Hands control to the next flycam.
+    pub cycle: KeyCode,
+    /// Switches the active flycam to the next [`FlycamMode`].
+    pub mode: KeyCode,
+}
+impl Default for FlycamBindings {
+    fn default() -> Self {
+        FlycamBindings {
+            forward: KeyCode::W,
+            back: KeyCode::S,
+            left: KeyCode::A,
+            right: KeyCode::D,
+            up: KeyCode::Space,
+            down: KeyCode::LControl,
+            boost: KeyCode::LShift,
+            toggle: KeyCode::Escape,
+            cycle: KeyCode::Tab,
+            mode: KeyCode::M,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FlycamConfigError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+}
+impl std::fmt::Display for FlycamConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlycamConfigError::Io(e) => write!(f, "failed to access flycam config: {}", e),
+            FlycamConfigError::Ron(e) => write!(f, "invalid flycam config: {}", e),
+        }
+    }
+}
+impl std::error::Error for FlycamConfigError {}
+
+/// A camera that can be flown around while it is the [`ActiveFlycam`].
+#[derive(Debug, Clone, Default, Reflect)]
+#[reflect(Component)]
+pub struct Flycam {
+    /// In degrees, taken from the camera's rotation when the component is added.
+    pub yaw: f32,
+    /// In degrees, positive looks down.
+    pub pitch: f32,
+    /// In units per second.
+    pub velocity: Vec3,
+    #[reflect(ignore)]
+    pub mode: FlycamMode,
+    /// The rotation last written by the flycam, to notice when something else rotates it.
+    #[reflect(ignore)]
+    rotation: Option<Quat>,
+    /// Set by [`Flycam::orbit`], so that the next update turns towards the focus.
+    #[reflect(ignore)]
+    face_focus: bool,
+}
+impl Flycam {
+    /// Switches to [`FlycamMode::Orbit`] around `focus`, e.g. `OrbitFocus::Entity(entity)` to
+    /// follow an entity. The next update turns the camera towards the focus and keeps its current
+    /// distance, which until then is 0.
+    pub fn orbit(&mut self, focus: OrbitFocus) {
+        self.mode = FlycamMode::Orbit {
+            focus,
+            distance: 0.0,
+        };
+        self.velocity = Vec3::ZERO;
+        self.face_focus = true;
+    }
+
+    fn look_along(&mut self, forward: Vec3) {
+        self.yaw = (-forward.x).atan2(-forward.z).to_degrees();
+        self.pitch = (-forward.y).clamp(-1.0, 1.0).asin().to_degrees();
+    }
+}
 
-pub struct Flycam;
+/// How a [`Flycam`] moves. [`FlycamBindings::mode`] switches between them, keeping the current
+/// view, and [`Flycam::orbit`] orbits a given point or entity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlycamMode {
+    /// Moves freely along the camera's axes.
+    Fly,
+    /// Rotates around `focus` at `distance`, which the mouse wheel zooms.
+    Orbit { focus: OrbitFocus, distance: f32 },
+    /// Moves on the ground plane at [`FlycamOptions::eye_height`]. Looking up or down doesn't
+    /// change the direction of movement. There is no collision, so switching to it from high up
+    /// makes the camera fall all the way down to the ground plane.
+    Walk,
+}
+impl Default for FlycamMode {
+    fn default() -> Self {
+        FlycamMode::Fly
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrbitFocus {
+    Point(Vec3),
+    /// Follows the entity's `GlobalTransform`.
+    Entity(Entity),
+}
+
+/// Marks the [`Flycam`] which is controlled by the input. If no flycam has it, the first one gets
+/// it, and [`FlycamBindings::cycle`] passes it on to the next.
+pub struct ActiveFlycam;
+
+fn init_flycams(mut flycams: Query<(&mut Flycam, &Transform), Added<Flycam>>) {
+    for (mut flycam, transform) in flycams.iter_mut() {
+        flycam.look_along(transform.forward());
+    }
+}
+
+fn cycle_active_flycam(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<FlycamOptions>,
+    flycams: Query<Entity, With<Flycam>>,
+    active_flycams: Query<Entity, (With<ActiveFlycam>, With<Flycam>)>,
+) {
+    let mut flycams: Vec<Entity> = flycams.iter().collect();
+    flycams.sort();
+
+    let active = active_flycams.iter().next();
+    let next = match active {
+        None => flycams.first().copied(),
+        Some(active) if keyboard_input.just_pressed(options.bindings.cycle) => flycams
+            .iter()
+            .position(|&flycam| flycam == active)
+            .map(|index| flycams[(index + 1) % flycams.len()]),
+        Some(_) => None,
+    };
+
+    if let Some(next) = next {
+        for entity in active_flycams.iter() {
+            commands.entity(entity).remove::<ActiveFlycam>();
+        }
+        commands.entity(next).insert(ActiveFlycam);
+    }
+}
+
+fn switch_flycam_mode(
+    keyboard_input: Res<Input<KeyCode>>,
+    options: Res<FlycamOptions>,
+    mut cams: Query<(&Transform, &mut Flycam), With<ActiveFlycam>>,
+) {
+    if !options.enabled || !keyboard_input.just_pressed(options.bindings.mode) {
+        return;
+    }
+
+    for (transform, mut flycam) in cams.iter_mut() {
+        flycam.mode = match flycam.mode {
+            // focus whatever is in front of the camera, so it doesn't move
+            FlycamMode::Fly => FlycamMode::Orbit {
+                focus: OrbitFocus::Point(
+                    transform.translation + transform.forward() * options.orbit_distance,
+                ),
+                distance: options.orbit_distance,
+            },
+            FlycamMode::Orbit { .. } => FlycamMode::Walk,
+            FlycamMode::Walk => FlycamMode::Fly,
+        };
+        flycam.velocity = Vec3::ZERO;
+        flycam.face_focus = false;
+    }
+}
 
 fn camera_movement(
-    mut cam: Query<&mut Transform, With<Flycam>>,
+    mut cams: Query<(&mut Transform, &mut Flycam), With<ActiveFlycam>>,
+    focuses: Query<&GlobalTransform>,
     time: Res<Time>,
     keyboard_input: Res<Input<KeyCode>>,
-
+    mut mouse_wheel_event_reader: EventReader<MouseWheel>,
     options: ResMut<FlycamOptions>,
 ) {
     if !options.enabled {
         return;
     }
 
-    let mut camera = cam.single_mut();
-
+    let bindings = &options.bindings;
     let if_then_1 = |b| if b { 1.0 } else { 0.0 };
-    let forward = if_then_1(keyboard_input.pressed(KeyCode::W))
-        - if_then_1(keyboard_input.pressed(KeyCode::S));
-    let sideways = if_then_1(keyboard_input.pressed(KeyCode::D))
-        - if_then_1(keyboard_input.pressed(KeyCode::A));
-    let up = if_then_1(keyboard_input.pressed(KeyCode::Space))
-        - if_then_1(keyboard_input.pressed(KeyCode::LControl));
-
-    if forward == 0.0 && sideways == 0.0 && up == 0.0 {
-        return;
-    }
+    let forward = if_then_1(keyboard_input.pressed(bindings.forward))
+        - if_then_1(keyboard_input.pressed(bindings.back));
+    let sideways = if_then_1(keyboard_input.pressed(bindings.right))
+        - if_then_1(keyboard_input.pressed(bindings.left));
+    let up = if_then_1(keyboard_input.pressed(bindings.up))
+        - if_then_1(keyboard_input.pressed(bindings.down));
 
-    let speed = if keyboard_input.pressed(KeyCode::LShift) {
-        20.0
+    let speed = if keyboard_input.pressed(bindings.boost) {
+        options.boost_speed
     } else {
-        5.0
+        options.speed
     };
+    let scroll: f32 = mouse_wheel_event_reader.iter().map(|event| event.y).sum();
+    let dt = time.delta_seconds();
+
+    for (mut transform, mut flycam) in cams.iter_mut() {
+        match flycam.mode {
+            FlycamMode::Fly => {
+                let movement = Vec3::new(sideways, forward, up).normalize_or_zero() * speed;
+                let target = transform.forward() * movement.y
+                    + transform.right() * movement.x
+                    + transform.up() * movement.z;
+                flycam.velocity = approach_velocity(flycam.velocity, target, &options, dt);
+                if flycam.velocity != Vec3::ZERO {
+                    transform.translation += flycam.velocity * dt;
+                }
+            }
+            FlycamMode::Orbit { focus, distance } => {
+                let (focus, focus_point) = match focus {
+                    OrbitFocus::Point(point) => (focus, point),
+                    OrbitFocus::Entity(entity) => match focuses.get(entity) {
+                        Ok(global_transform) => (focus, global_transform.translation),
+                        // the entity is gone, keep orbiting where it was
+                        Err(_) => {
+                            let point = transform.translation + transform.forward() * distance;
+                            (OrbitFocus::Point(point), point)
+                        }
+                    },
+                };
+                let mut distance = distance;
+                if flycam.face_focus {
+                    flycam.face_focus = false;
+                    distance = transform.translation.distance(focus_point);
+                    // looking at itself has no direction
+                    if distance > f32::EPSILON {
+                        transform.look_at(focus_point, Vec3::Y);
+                        let forward = transform.forward();
+                        flycam.look_along(forward);
+                    }
+                }
+                let distance = (distance * (-scroll * options.zoom_sensitivity).exp()).max(0.1);
+                flycam.mode = FlycamMode::Orbit { focus, distance };
+                flycam.velocity = Vec3::ZERO;
+                transform.translation = focus_point - transform.forward() * distance;
+            }
+            FlycamMode::Walk => {
+                let movement = Vec2::new(sideways, forward).normalize_or_zero() * speed;
+                // only the yaw turns the direction of movement
+                let yaw = flycam.yaw.to_radians();
+                let walk_forward = Vec3::new(-yaw.sin(), 0.0, -yaw.cos());
+                let walk_right = Vec3::new(yaw.cos(), 0.0, -yaw.sin());
+                let target = walk_forward * movement.y + walk_right * movement.x;
+                let current = Vec3::new(flycam.velocity.x, 0.0, flycam.velocity.z);
+                let horizontal = approach_velocity(current, target, &options, dt);
 
-    let movement =
-        Vec3::new(sideways, forward, up).normalize_or_zero() * speed * time.delta_seconds();
+                let eye_level = options.ground_height + options.eye_height;
+                let grounded = transform.translation.y <= eye_level;
+                let vertical = if grounded && keyboard_input.just_pressed(bindings.up) {
+                    options.jump_speed
+                } else if grounded {
+                    0.0
+                } else {
+                    flycam.velocity.y - options.gravity * dt
+                };
+                flycam.velocity = Vec3::new(horizontal.x, vertical, horizontal.z);
 
-    let diff =
-        camera.forward() * movement.y + camera.right() * movement.x + camera.up() * movement.z;
-    camera.translation += diff;
+                transform.translation += flycam.velocity * dt;
+                if transform.translation.y < eye_level {
+                    transform.translation.y = eye_level;
+                    flycam.velocity.y = 0.0;
+                }
+            }
+        }
+    }
+}
+
+/// Accelerates towards `target` or damps towards standing still with the time constants in
+/// `options`.
+fn approach_velocity(current: Vec3, target: Vec3, options: &FlycamOptions, dt: f32) -> Vec3 {
+    let time_constant = if target != Vec3::ZERO {
+        options.acceleration_time
+    } else {
+        options.damping_time
+    };
+    let velocity = current.lerp(target, smoothing_factor(time_constant, dt));
+    // exponential damping never quite reaches zero
+    if target == Vec3::ZERO && velocity.length_squared() < 1e-6 {
+        Vec3::ZERO
+    } else {
+        velocity
+    }
+}
+
+/// How far to move towards a target within `dt` seconds, to cover ~63% of the way every
+/// `time_constant` seconds.
+fn smoothing_factor(time_constant: f32, dt: f32) -> f32 {
+    if time_constant <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-dt / time_constant).exp()
+    }
 }
 
 fn camera_look(
     time: Res<Time>,
     mut mouse_motion_event_reader: EventReader<MouseMotion>,
-    mut query: Query<&mut Transform, With<Flycam>>,
-    mut options: ResMut<FlycamOptions>,
+    mut query: Query<(&mut Transform, &mut Flycam), With<ActiveFlycam>>,
+    options: Res<FlycamOptions>,
 ) {
     if !options.enabled {
         return;
@@ -84,22 +435,45 @@ fn camera_look(
     for event in mouse_motion_event_reader.iter() {
         delta += event.delta;
     }
-    if delta.is_nan() || delta.abs_diff_eq(Vec2::ZERO, f32::EPSILON) {
-        return;
+    if delta.is_nan() {
+        delta = Vec2::ZERO;
     }
 
-    for mut transform in query.iter_mut() {
-        options.yaw -= delta.x * options.sensitivity * time.delta_seconds();
-        options.pitch += delta.y * options.sensitivity * time.delta_seconds();
+    for (mut transform, mut flycam) in query.iter_mut() {
+        // rotated elsewhere, e.g. by a portal or the inspector, so look on from there
+        if flycam
+            .rotation
+            .map_or(false, |rotation| rotation != transform.rotation)
+        {
+            flycam.look_along(transform.forward());
+        }
+
+        // the deltas already add up to the motion since the last frame
+        flycam.yaw -= delta.x * options.degrees_per_pixel;
+        let delta_y = if options.invert_y { -delta.y } else { delta.y };
+        flycam.pitch += delta_y * options.degrees_per_pixel;
 
-        options.pitch = options.pitch.clamp(-89.0, 89.9);
-        // println!("pitch: {}, yaw: {}", options.pitch, options.yaw);
+        flycam.pitch = flycam.pitch.clamp(-89.0, 89.9);
 
-        let yaw_radians = options.yaw.to_radians();
-        let pitch_radians = options.pitch.to_radians();
+        let yaw_radians = flycam.yaw.to_radians();
+        let pitch_radians = flycam.pitch.to_radians();
 
-        transform.rotation = Quat::from_axis_angle(Vec3::Y, yaw_radians)
+        let mut target = Quat::from_axis_angle(Vec3::Y, yaw_radians)
             * Quat::from_axis_angle(-Vec3::X, pitch_radians);
+        // slerp the short way around
+        if transform.rotation.dot(target) < 0.0 {
+            target = -target;
+        }
+        let factor = smoothing_factor(options.look_smoothing, time.delta_seconds());
+        let rotation = if transform.rotation.abs_diff_eq(target, 1e-5) {
+            target
+        } else {
+            transform.rotation.slerp(target, factor)
+        };
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+        flycam.rotation = Some(rotation);
     }
 }
 
@@ -108,7 +482,11 @@ fn toggle_cursor(
     mut windows: ResMut<Windows>,
 ) {
     for &WindowFocused { id, focused } in window_focused_events.iter() {
-        let window = windows.get_mut(id).unwrap();
+        // the window may have been closed since
+        let window = match windows.get_mut(id) {
+            Some(window) => window,
+            None => continue,
+        };
         window.set_cursor_lock_mode(focused);
         window.set_cursor_visibility(!focused);
     }
@@ -116,14 +494,139 @@ fn toggle_cursor(
 
 fn toggle_cursor_manually(
     keyboard_events: Res<Input<KeyCode>>,
+    mut window_focused_events: EventReader<WindowFocused>,
+    mut focused_window: Local<Option<WindowId>>,
     mut windows: ResMut<Windows>,
     mut flycam_options: ResMut<FlycamOptions>,
 ) {
-    if keyboard_events.just_pressed(KeyCode::Escape) {
+    for event in window_focused_events.iter().filter(|event| event.focused) {
+        *focused_window = Some(event.id);
+    }
+
+    if keyboard_events.just_pressed(flycam_options.bindings.toggle) {
         flycam_options.enabled = !flycam_options.enabled;
 
-        let window = windows.get_primary_mut().unwrap();
+        // the key press went to the focused window
+        let window_id = focused_window.unwrap_or_else(WindowId::primary);
+        let window = match windows.get_mut(window_id) {
+            Some(window) => window,
+            None => return,
+        };
         window.set_cursor_lock_mode(!window.cursor_locked());
         window.set_cursor_visibility(!window.cursor_visible());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_config_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}_{}.ron", name, std::process::id()))
+    }
+
+    #[test]
+    fn options_round_trip() {
+        let options = FlycamOptions {
+            speed: 7.5,
+            invert_y: true,
+            bindings: FlycamBindings {
+                cycle: KeyCode::Q,
+                ..Default::default()
+            },
+            enabled: false,
+            ..Default::default()
+        };
+        let path = temp_config_path("flycam_round_trip");
+        options.save(&path).unwrap();
+        let loaded = FlycamOptions::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.speed, 7.5);
+        assert!(loaded.invert_y);
+        assert_eq!(loaded.bindings, options.bindings);
+        // not saved, so a loaded flycam starts out enabled
+        assert!(loaded.enabled);
+    }
+
+    #[test]
+    fn partial_config_keeps_defaults() {
+        let path = temp_config_path("flycam_partial");
+        std::fs::write(&path, "(speed: 8.0, bindings: (forward: Up))").unwrap();
+        let loaded = FlycamOptions::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let defaults = FlycamOptions::default();
+        assert_eq!(loaded.speed, 8.0);
+        assert_eq!(loaded.degrees_per_pixel, defaults.degrees_per_pixel);
+        assert_eq!(loaded.boost_speed, defaults.boost_speed);
+        assert_eq!(loaded.bindings.forward, KeyCode::Up);
+        assert_eq!(loaded.bindings.back, defaults.bindings.back);
+        assert!(loaded.enabled);
+    }
+
+    #[test]
+    fn orbit_keeps_distance_and_faces_focus() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<FlycamOptions>()
+            .add_event::<MouseWheel>()
+            .add_system(camera_movement);
+
+        let focus = OrbitFocus::Entity(
+            app.world
+                .spawn()
+                .insert(GlobalTransform::from_xyz(0.0, 1.0, 0.0))
+                .id(),
+        );
+        let mut flycam = Flycam::default();
+        flycam.orbit(focus);
+        let camera = app
+            .world
+            .spawn()
+            .insert_bundle((Transform::from_xyz(0.0, 11.0, 10.0), flycam, ActiveFlycam))
+            .id();
+        app.update();
+
+        let flycam = app.world.get::<Flycam>(camera).unwrap();
+        let transform = app.world.get::<Transform>(camera).unwrap();
+        let distance = 200f32.sqrt();
+        assert_eq!(flycam.mode, FlycamMode::Orbit { focus, distance });
+        assert!(transform
+            .translation
+            .abs_diff_eq(Vec3::new(0.0, 11.0, 10.0), 1e-4));
+        assert!(transform
+            .forward()
+            .abs_diff_eq(Vec3::new(0.0, -1.0, -1.0) / 2f32.sqrt(), 1e-5));
+        assert!(flycam.yaw.abs() < 1e-3);
+        assert!((flycam.pitch - 45.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn smoothing_is_independent_of_frame_rate() {
+        let once = smoothing_factor(0.3, 0.1);
+        let remaining = (0..10).fold(1.0, |remaining, _| {
+            remaining * (1.0 - smoothing_factor(0.3, 0.01))
+        });
+        assert!((once - (1.0 - remaining)).abs() < 1e-5);
+        assert_eq!(smoothing_factor(0.0, 0.01), 1.0);
+    }
+
+    #[test]
+    fn velocity_is_independent_of_frame_rate() {
+        let options = FlycamOptions {
+            acceleration_time: 0.2,
+            damping_time: 0.5,
+            ..Default::default()
+        };
+        for &target in &[Vec3::new(5.0, 0.0, -2.0), Vec3::ZERO] {
+            let current = Vec3::new(1.0, 2.0, 3.0);
+            let once = approach_velocity(current, target, &options, 0.1);
+            let stepped = (0..10).fold(current, |velocity, _| {
+                approach_velocity(velocity, target, &options, 0.01)
+            });
+            assert!(once.abs_diff_eq(stepped, 1e-4), "{} != {}", once, stepped);
+        }
+    }
+}
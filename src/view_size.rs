@@ -0,0 +1,218 @@
+use bevy::ecs::prelude::*;
+use bevy::ecs::system::lifetimeless::*;
+use bevy::ecs::system::SystemParamItem;
+use bevy::math::Vec2;
+use bevy::prelude::{App, Plugin, Time};
+use bevy::render2::render_phase::{PhaseItem, RenderCommand, TrackedRenderPass};
+use bevy::render2::render_resource::*;
+use bevy::render2::renderer::{RenderDevice, RenderQueue};
+use bevy::render2::view::ExtractedView;
+use bevy::render2::{RenderApp, RenderStage};
+
+use crevice::std140::AsStd140;
+
+/// Makes a [`ViewSize`] uniform available for every extracted view.
+///
+/// Pipelines that want it add [`ViewSizeMeta::layout`] to their pipeline layout and bind it
+/// with [`SetViewSizeBindGroup`]. The WGSL side looks like this:
+///
+/// ```wgsl
+/// [[block]]
+/// struct ViewSize {
+///     size: vec2<f32>;
+///     inverse_size: vec2<f32>;
+///     viewport_offset: vec2<f32>;
+///     aspect_ratio: f32;
+///     time: f32;
+///     frame: u32;
+/// };
+/// [[group(3), binding(0)]]
+/// var<uniform> view_size: ViewSize;
+/// ```
+///
+/// Adding the plugin more than once is harmless, so plugins depending on it can add it themselves.
+pub struct ViewSizePlugin;
+
+impl Plugin for ViewSizePlugin {
+    fn build(&self, app: &mut App) {
+        let render_app = app.sub_app(RenderApp);
+        if render_app.world.contains_resource::<ViewSizeMeta>() {
+            return;
+        }
+
+        render_app
+            .add_system_to_stage(RenderStage::Extract, extract_time)
+            .init_resource::<ExtractedTime>()
+            .init_resource::<ViewSizeMeta>()
+            .init_resource::<ViewSizeUniforms>()
+            .add_system_to_stage(
+                RenderStage::Prepare,
+                prepare_view_sizes.label(ViewSizeSystem::Prepare),
+            )
+            .add_system_to_stage(
+                RenderStage::Queue,
+                queue_view_sizes.label(ViewSizeSystem::Queue),
+            );
+    }
+}
+
+/// Systems reading the [`ViewSizeUniformOffset`]s or [`ViewSizeMeta::bind_group`] outside of
+/// the render phase order themselves after these.
+#[derive(SystemLabel, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ViewSizeSystem {
+    /// Writes the uniforms of this frame's views, in [`RenderStage::Prepare`].
+    Prepare,
+    /// Creates the bind group, in [`RenderStage::Queue`].
+    Queue,
+}
+
+#[derive(AsStd140)]
+pub struct ViewSize {
+    /// Size of the view in pixels.
+    pub size: Vec2,
+    pub inverse_size: Vec2,
+    /// Position of the view's top left corner in the window it is shown in, in pixels. Zero for
+    /// views covering their whole window; screen space uvs don't need it.
+    pub viewport_offset: Vec2,
+    /// Width divided by height.
+    pub aspect_ratio: f32,
+    /// Seconds since startup.
+    pub time: f32,
+    /// Number of frames rendered so far, wrapping on overflow.
+    pub frame: u32,
+}
+
+/// Position of a view in the window it is shown in, for views that only cover part of it, like
+/// the cameras of a [`Viewport`](crate::viewport::Viewport).
+///
+/// Lives in the render world, on the entity of the [`ExtractedView`]. Views without it start
+/// at the origin.
+#[derive(Clone, Copy, Default)]
+pub struct ViewportOffset(pub Vec2);
+
+pub struct ViewSizeUniformOffset(pub u32);
+
+#[derive(Default)]
+pub struct ViewSizeUniforms {
+    pub uniforms: DynamicUniformVec<ViewSize>,
+}
+
+impl ViewSizeUniforms {
+    fn push(&mut self, value: ViewSize) -> ViewSizeUniformOffset {
+        let offset = self.uniforms.push(value);
+        ViewSizeUniformOffset(offset)
+    }
+}
+
+pub struct ViewSizeMeta {
+    pub layout: BindGroupLayout,
+    pub bind_group: Option<BindGroup>,
+}
+
+impl FromWorld for ViewSizeMeta {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+
+        let layout = render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStage::VERTEX | ShaderStage::FRAGMENT,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: BufferSize::new(ViewSize::std140_size_static() as u64),
+                },
+                count: None,
+            }],
+        });
+
+        ViewSizeMeta {
+            layout,
+            bind_group: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ExtractedTime {
+    seconds_since_startup: f32,
+    frame: u32,
+}
+
+fn extract_time(mut commands: Commands, time: Res<Time>, mut frame: Local<u32>) {
+    commands.insert_resource(ExtractedTime {
+        seconds_since_startup: time.seconds_since_startup() as f32,
+        frame: *frame,
+    });
+    *frame = frame.wrapping_add(1);
+}
+
+pub fn prepare_view_sizes(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut view_size_uniforms: ResMut<ViewSizeUniforms>,
+    time: Res<ExtractedTime>,
+    extracted_views: Query<(Entity, &ExtractedView, Option<&ViewportOffset>)>,
+) {
+    view_size_uniforms
+        .uniforms
+        .reserve_and_clear(extracted_views.iter().len(), &render_device);
+
+    for (entity, view, viewport_offset) in extracted_views.iter() {
+        let size = Vec2::new(view.width as f32, view.height as f32).max(Vec2::ONE);
+        let offset = view_size_uniforms.push(ViewSize {
+            size,
+            inverse_size: size.recip(),
+            viewport_offset: viewport_offset.copied().unwrap_or_default().0,
+            aspect_ratio: size.x / size.y,
+            time: time.seconds_since_startup,
+            frame: time.frame,
+        });
+
+        commands.entity(entity).insert(offset);
+    }
+
+    view_size_uniforms.uniforms.write_buffer(&render_queue);
+}
+
+pub fn queue_view_sizes(
+    mut view_size_meta: ResMut<ViewSizeMeta>,
+    view_size_uniforms: Res<ViewSizeUniforms>,
+    render_device: Res<RenderDevice>,
+) {
+    let view_size_binding = match view_size_uniforms.uniforms.binding() {
+        Some(val) => val,
+        None => return,
+    };
+
+    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &view_size_meta.layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: view_size_binding,
+        }],
+    });
+    view_size_meta.bind_group = Some(bind_group);
+}
+
+/// Binds the [`ViewSize`] uniform of the current view at group `I`.
+pub struct SetViewSizeBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetViewSizeBindGroup<I> {
+    type Param = (SRes<ViewSizeMeta>, SQuery<Read<ViewSizeUniformOffset>>);
+
+    fn render<'w>(
+        view: Entity,
+        _: &P,
+        (meta, view_size_offsets): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) {
+        let view_size_offset = view_size_offsets.get(view).unwrap();
+        let view_size_bind_group = meta.into_inner().bind_group.as_ref().unwrap();
+
+        pass.set_bind_group(I, view_size_bind_group, &[view_size_offset.0]);
+    }
+}
@@ -0,0 +1,400 @@
+use bevy::core_pipeline::node::MAIN_PASS_DRIVER;
+use bevy::ecs::prelude::*;
+use bevy::math::Vec2;
+use bevy::prelude::{App, Assets, Handle, Plugin};
+use bevy::render2::camera::Camera;
+use bevy::render2::render_asset::RenderAssets;
+use bevy::render2::render_graph::{self, RenderGraph, RenderGraphContext};
+use bevy::render2::render_resource::*;
+use bevy::render2::renderer::{RenderContext, RenderDevice, RenderQueue};
+use bevy::render2::shader::Shader;
+use bevy::render2::texture::{BevyDefault, Image};
+use bevy::render2::view::ExtractedWindows;
+use bevy::render2::{RenderApp, RenderStage};
+use bevy::window::{WindowId, Windows};
+
+use crevice::std140::AsStd140;
+
+use crate::render_to_texture::{
+    is_renderable, RenderTargetSize, RenderToTexture, RenderToTextureSystem,
+};
+use crate::view_size::ViewportOffset;
+
+pub mod node {
+    pub const VIEWPORT_COMPOSITE: &str = "viewport_composite_node";
+}
+
+/// Draws the image of a [`RenderToTexture`] camera into a rectangle of the camera's window,
+/// on top of what the window's own camera rendered. Used for split screen and
+/// picture-in-picture insets.
+///
+/// The camera's images are sized to the rectangle, so its [`RenderTargetSize`] is managed by the
+/// [`ViewportPlugin`]. Since the camera renders into its own images rather than a part of the
+/// window, its [`ViewSize`](crate::view_size::ViewSize) covers just the viewport and
+/// screen space displays seen by it sample the right coordinates. Its `viewport_offset` is
+/// where the viewport sits in the window. Portal cameras seen by it
+/// should use [`RenderTargetSize::MatchCamera`].
+///
+/// The camera shouldn't be one of bevy's active cameras, which are also rendered to the whole
+/// window, e.g. use `PerspectiveCameraBundle::with_name("player 2")`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Top left corner, as a fraction of the window size.
+    pub position: Vec2,
+    /// As a fraction of the window size.
+    pub size: Vec2,
+    /// Viewports with a higher order are drawn on top of the others.
+    pub order: i32,
+}
+
+impl Viewport {
+    pub fn new(position: Vec2, size: Vec2) -> Self {
+        Viewport {
+            position,
+            size,
+            order: 0,
+        }
+    }
+
+    /// Cell `index` of a grid with `columns` by `rows` cells, counted row by row from the top
+    /// left. `grid(2, 1, i)` splits the window for two players, `grid(2, 2, i)` for four.
+    pub fn grid(columns: u32, rows: u32, index: u32) -> Self {
+        let size = Vec2::new(1.0 / columns as f32, 1.0 / rows as f32);
+        let cell = Vec2::new((index % columns) as f32, (index / columns) as f32);
+        Viewport::new(cell * size, size)
+    }
+}
+
+/// Composites every [`Viewport`] camera into its window after the main pass.
+///
+/// Requires the [`RenderToTexturePlugin`](crate::render_to_texture::RenderToTexturePlugin).
+pub struct ViewportPlugin;
+impl Plugin for ViewportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(update_viewport_sizes.before(RenderToTextureSystem::ResizeTexture));
+
+        let render_app = app.sub_app(RenderApp);
+        render_app
+            .init_resource::<ExtractedViewports>()
+            .init_resource::<ViewportCompositor>()
+            .add_system_to_stage(RenderStage::Extract, extract_viewports)
+            .add_system_to_stage(RenderStage::Queue, queue_viewports);
+
+        let mut render_graph = render_app.world.get_resource_mut::<RenderGraph>().unwrap();
+        render_graph.add_node(node::VIEWPORT_COMPOSITE, ViewportCompositeNode);
+        render_graph
+            .add_node_edge(MAIN_PASS_DRIVER, node::VIEWPORT_COMPOSITE)
+            .unwrap();
+    }
+}
+
+fn update_viewport_sizes(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    mut cams: Query<
+        (Entity, &Camera, &Viewport, Option<&mut RenderTargetSize>),
+        With<RenderToTexture>,
+    >,
+) {
+    for (entity, camera, viewport, target_size) in cams.iter_mut() {
+        let window = match windows.get(camera.window) {
+            Some(window) => window,
+            None => continue,
+        };
+        let window_size = Vec2::new(
+            window.physical_width() as f32,
+            window.physical_height() as f32,
+        );
+
+        let size = (viewport.size * window_size).round();
+        let size = RenderTargetSize::Fixed {
+            width: size.x as u32,
+            height: size.y as u32,
+        };
+        // written in place so that the images are resized this frame
+        match target_size {
+            Some(mut target_size) => {
+                if *target_size != size {
+                    *target_size = size;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(size);
+            }
+        }
+    }
+}
+
+struct ExtractedViewport {
+    window: WindowId,
+    /// The image rendered this frame.
+    image: Handle<Image>,
+    viewport: Viewport,
+}
+
+#[derive(Default)]
+struct ExtractedViewports(Vec<ExtractedViewport>);
+
+fn extract_viewports(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    images: Res<Assets<Image>>,
+    cams: Query<(Entity, &Camera, &RenderToTexture, &Viewport)>,
+) {
+    let mut viewports = Vec::new();
+    for (entity, camera, render_to_texture, viewport) in cams.iter() {
+        // cameras which aren't rendered would composite a blank image
+        let is_rendered = images
+            .get(&render_to_texture.render_target)
+            .map_or(false, is_renderable);
+        if !is_rendered {
+            continue;
+        }
+        if let Some(window) = windows.get(camera.window) {
+            let window_size = Vec2::new(
+                window.physical_width() as f32,
+                window.physical_height() as f32,
+            );
+            commands
+                .get_or_spawn(entity)
+                .insert(ViewportOffset((viewport.position * window_size).round()));
+        }
+        viewports.push(ExtractedViewport {
+            window: camera.window,
+            image: render_to_texture.render_target.clone_weak(),
+            viewport: *viewport,
+        });
+    }
+    viewports.sort_by_key(|extracted| extracted.viewport.order);
+
+    commands.insert_resource(ExtractedViewports(viewports));
+}
+
+#[derive(Clone, AsStd140)]
+struct ViewportRect {
+    position: Vec2,
+    size: Vec2,
+}
+
+struct ViewportDraw {
+    window: WindowId,
+    bind_group: BindGroup,
+    uniform_offset: u32,
+}
+
+/// The pipeline drawing images into viewports, and this frame's draws.
+pub struct ViewportCompositor {
+    layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    uniforms: DynamicUniformVec<ViewportRect>,
+    draws: Vec<ViewportDraw>,
+}
+
+impl FromWorld for ViewportCompositor {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.get_resource::<RenderDevice>().unwrap();
+        let shader = Shader::from_wgsl(include_str!("../assets/viewport.wgsl"));
+        let shader_module = render_device.create_shader_module(&shader);
+
+        let layout =
+            render_device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                entries: &[
+                    // texture
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    // sampler
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStage::FRAGMENT,
+                        ty: BindingType::Sampler {
+                            comparison: false,
+                            filtering: true,
+                        },
+                        count: None,
+                    },
+                    // viewport rect
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStage::VERTEX,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: BufferSize::new(
+                                ViewportRect::std140_size_static() as u64
+                            ),
+                        },
+                        count: None,
+                    },
+                ],
+                label: None,
+            });
+
+        let pipeline_layout = render_device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&layout],
+        });
+
+        let pipeline = render_device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            vertex: VertexState {
+                buffers: &[],
+                module: &shader_module,
+                entry_point: "vertex",
+            },
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: "fragment",
+                targets: &[ColorTargetState {
+                    format: TextureFormat::bevy_default(),
+                    blend: None,
+                    write_mask: ColorWrite::ALL,
+                }],
+            }),
+            depth_stencil: None,
+            layout: Some(&pipeline_layout),
+            multisample: MultisampleState::default(),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                front_face: FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                clamp_depth: false,
+                conservative: false,
+            },
+        });
+
+        ViewportCompositor {
+            layout,
+            pipeline,
+            uniforms: DynamicUniformVec::default(),
+            draws: Vec::new(),
+        }
+    }
+}
+
+fn queue_viewports(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    gpu_images: Res<RenderAssets<Image>>,
+    viewports: Res<ExtractedViewports>,
+    mut compositor: ResMut<ViewportCompositor>,
+) {
+    let compositor = &mut *compositor;
+    compositor.draws.clear();
+    compositor
+        .uniforms
+        .reserve_and_clear(viewports.0.len(), &render_device);
+
+    let mut uniform_offsets = Vec::with_capacity(viewports.0.len());
+    for extracted in &viewports.0 {
+        uniform_offsets.push(compositor.uniforms.push(ViewportRect {
+            position: extracted.viewport.position,
+            size: extracted.viewport.size,
+        }));
+    }
+    compositor.uniforms.write_buffer(&render_queue);
+
+    let uniform_buffer = match compositor.uniforms.uniform_buffer() {
+        Some(uniform_buffer) => uniform_buffer,
+        None => return,
+    };
+    for (extracted, uniform_offset) in viewports.0.iter().zip(uniform_offsets) {
+        // the camera is skipped as well if its image isn't ready
+        let gpu_image = match gpu_images.get(&extracted.image) {
+            Some(gpu_image) => gpu_image,
+            None => continue,
+        };
+
+        let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&gpu_image.texture_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&gpu_image.sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::Buffer(BufferBinding {
+                        buffer: uniform_buffer,
+                        offset: 0,
+                        size: BufferSize::new(ViewportRect::std140_size_static() as u64),
+                    }),
+                },
+            ],
+            label: None,
+            layout: &compositor.layout,
+        });
+
+        compositor.draws.push(ViewportDraw {
+            window: extracted.window,
+            bind_group,
+            uniform_offset,
+        });
+    }
+}
+
+/// Draws the [`Viewport`] cameras into their windows, in order.
+struct ViewportCompositeNode;
+
+impl render_graph::Node for ViewportCompositeNode {
+    fn run(
+        &self,
+        _: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let compositor = world.get_resource::<ViewportCompositor>().unwrap();
+        let extracted_windows = world.get_resource::<ExtractedWindows>().unwrap();
+
+        for (window_id, extracted_window) in extracted_windows.iter() {
+            let swap_chain_texture = match &extracted_window.swap_chain_frame {
+                Some(swap_chain_texture) => swap_chain_texture,
+                None => continue,
+            };
+            let mut draws = compositor
+                .draws
+                .iter()
+                .filter(|draw| draw.window == *window_id)
+                .peekable();
+            if draws.peek().is_none() {
+                continue;
+            }
+
+            let mut pass =
+                render_context
+                    .command_encoder
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("viewport_composite_pass"),
+                        color_attachments: &[RenderPassColorAttachment {
+                            view: swap_chain_texture,
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Load,
+                                store: true,
+                            },
+                        }],
+                        depth_stencil_attachment: None,
+                    });
+            pass.set_pipeline(&compositor.pipeline);
+            for draw in draws {
+                pass.set_bind_group(0, &draw.bind_group, &[draw.uniform_offset]);
+                pass.draw(0..4, 0..1);
+            }
+        }
+
+        Ok(())
+    }
+}